@@ -1,32 +1,43 @@
-use steamguard::steamapi::ApiRequest;
-use steamguard::token::Tokens;
-use steamguard::transport::{Transport, WebApiTransport};
-
+use super::link_details::get_player_link_details;
 use crate::AppState;
 use crate::account::payloads::{GetProfileError, ProfileResponse};
-use crate::protobufs::steammessages_player_steamclient::{
-    CPlayer_GetPlayerLinkDetails_Request, CPlayer_GetPlayerLinkDetails_Response,
-};
+use crate::avatars::download::download_avatar;
 
-/// Profile picture WIP
 #[tauri::command]
 pub fn get_profile(state: tauri::State<'_, AppState>) -> Result<ProfileResponse, GetProfileError> {
-    let accounts_config = state.accounts_config.lock().unwrap();
-    let active_account = accounts_config
-        .get_active_account()
-        .ok_or(GetProfileError::NoValidAccount)?;
-    let transport = WebApiTransport::new(reqwest::blocking::Client::new());
-    let tokens = Tokens::new(
-        active_account.access_token.clone(),
-        active_account.refresh_token.clone(),
-    );
+    let active_account = {
+        let accounts_config = state.accounts_config.lock().unwrap();
+        if accounts_config.is_locked() {
+            return Err(GetProfileError::Locked);
+        }
+        accounts_config
+            .get_active_account()
+            .cloned()
+            .ok_or(GetProfileError::NoValidAccount)?
+    };
 
-    let request = CPlayer_GetPlayerLinkDetails_Request {
-        steamids: vec![active_account.steam_id],
-        ..Default::default()
+    let details = get_player_link_details(
+        state.transport.clone(),
+        &active_account,
+        vec![active_account.steam_id],
+    )?
+    .pop()
+    .ok_or(GetProfileError::NetworkError)?;
+    let steam_id = active_account.steam_id;
+    let avatar_path = if state.avatar_cache.is_expired(steam_id) {
+        download_avatar(
+            &state.http_client,
+            &state.avatar_cache,
+            steam_id,
+            details.public_data.sha_digest_avatar(),
+        )
+        .inspect_err(|err| log::warn!("Could not download the avatar: {err:?}"))
+        .ok()
+    } else {
+        state.avatar_cache.path(steam_id)
     };
-    let request = ApiRequest::new("IPlayerService", "GetPlayerLinkDetails", 1, request)
-        .with_access_token(tokens.access_token());
-    let response = transport.send_request::<CPlayer_GetPlayerLinkDetails_Request, CPlayer_GetPlayerLinkDetails_Response>(request)?;
-    Ok(response.into_response_data().accounts.pop().unwrap().into())
+
+    let mut profile = ProfileResponse::from(details);
+    profile.avatar_url = avatar_path.map(|path| path.to_string_lossy().into_owned());
+    Ok(profile)
 }
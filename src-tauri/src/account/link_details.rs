@@ -0,0 +1,29 @@
+use steamguard::steamapi::ApiRequest;
+use steamguard::transport::{Transport, TransportError};
+
+use crate::auth::user_credentials::UserCredentials;
+use crate::protobufs::steammessages_player_steamclient::{
+    CPlayer_GetPlayerLinkDetails_Request, CPlayer_GetPlayerLinkDetails_Response,
+    cplayer_get_player_link_details_response::PlayerLinkDetails,
+};
+
+/// Public profile data of `steam_ids`, authenticated as `account`. Private data is only
+/// filled in for `account` itself.
+pub fn get_player_link_details(
+    transport: impl Transport,
+    account: &UserCredentials,
+    steam_ids: Vec<u64>,
+) -> Result<Vec<PlayerLinkDetails>, TransportError> {
+    let tokens = account.tokens();
+    let request = CPlayer_GetPlayerLinkDetails_Request {
+        steamids: steam_ids,
+        ..Default::default()
+    };
+    let request = ApiRequest::new("IPlayerService", "GetPlayerLinkDetails", 1, request)
+        .with_access_token(tokens.access_token());
+    let response = transport.send_request::<
+        CPlayer_GetPlayerLinkDetails_Request,
+        CPlayer_GetPlayerLinkDetails_Response,
+    >(request)?;
+    Ok(response.into_response_data().accounts)
+}
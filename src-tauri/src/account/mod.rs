@@ -1,2 +1,3 @@
 pub mod commands;
+pub mod link_details;
 pub mod payloads;
@@ -13,6 +13,7 @@ use crate::{
 #[serde(rename_all = "kebab-case")]
 pub enum GetProfileError {
     NoValidAccount,
+    Locked,
     NetworkError,
 }
 
@@ -22,6 +23,8 @@ pub struct ProfileResponse {
     pub steam_id: u64,
     pub persona_name: Option<String>,
     pub profile_url: Option<String>,
+    /// Local path of the cached image
+    pub avatar_url: Option<String>,
     pub account_name: String,
 }
 
@@ -31,6 +34,7 @@ impl From<PlayerLinkDetails> for ProfileResponse {
             steam_id: value.public_data.steamid(),
             persona_name: Some(value.public_data.persona_name().to_string()),
             profile_url: Some(value.public_data.profile_url().to_string()),
+            avatar_url: None,
             account_name: value.private_data.account_name().to_string(),
         }
     }
@@ -0,0 +1,401 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use zeroize::Zeroizing;
+
+use super::accounts_config::AccountsInitError;
+use super::migrations::{CONFIG_VERSION, MigrationError, migrate_account};
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::atomic_write::write_atomically;
+use crate::common::secret::wipe_json;
+use crate::config_backups::rotation::{remove_backups, rotate_backups};
+use crate::secret_store::store::{
+    AccountSecrets, SecretStore, SecretStoreError, load_secrets, store_secrets,
+};
+use crate::vault::crypto::VaultKey;
+use crate::vault::payloads::VaultError;
+
+/// On-disk shape of `accounts/{account_name}.json`, exactly one of `account`, `keyring` and
+/// `sealed` is set. Read with the account as a [`RawValue`] and written with a reference to
+/// it, so the secrets are never copied into an untyped `Value`.
+#[derive(Serialize, Deserialize)]
+struct AccountFile<A> {
+    version: u32,
+    /// Kept outside the sealed part, so a locked vault still knows its accounts
+    account_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<A>,
+    /// The account without its secrets, those are in the platform secret store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyring: Option<A>,
+    /// The account encrypted with the vault key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sealed: Option<String>,
+}
+
+/// An account file as read from disk
+pub enum StoredAccount {
+    Plain(UserCredentials),
+    /// Put back together with its secrets from the secret store
+    Keyring(UserCredentials),
+    Sealed(SealedAccount),
+}
+
+/// Where [`write_account_file`] puts the secrets of an account
+#[derive(Clone, Copy)]
+pub enum SecretTarget<'a> {
+    File,
+    Sealed(&'a VaultKey),
+    Keyring(&'a dyn SecretStore),
+}
+
+#[derive(Debug, Clone)]
+pub struct SealedAccount {
+    pub account_name: String,
+    /// Config version the account was sealed with. It can only be migrated once opened.
+    pub version: u32,
+    pub sealed: String,
+}
+
+impl SealedAccount {
+    pub fn open(&self, vault_key: &VaultKey) -> Result<UserCredentials, VaultError> {
+        let plaintext = vault_key.open(&self.sealed)?;
+        deserialize_account(&plaintext, self.version).map_err(|_| VaultError::Corrupted)
+    }
+}
+
+/// Accounts live next to the manifest, one file each
+pub fn accounts_dir(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("accounts")
+}
+
+pub fn account_path(config_path: &Path, account_name: &str) -> PathBuf {
+    accounts_dir(config_path).join(format!("{}.json", file_stem(account_name)))
+}
+
+/// Steam only allows letters, digits and underscores in account names. Anything else is
+/// hex encoded, so an imported name cannot point outside the directory.
+pub fn file_stem(account_name: &str) -> String {
+    let is_plain = !account_name.is_empty()
+        && account_name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_');
+    if is_plain {
+        account_name.to_string()
+    } else {
+        format!("x-{}", hex::encode(account_name))
+    }
+}
+
+/// Reverses [`file_stem`], for files whose content cannot tell the account name
+pub fn account_name_from_stem(file_stem: &str) -> String {
+    file_stem
+        .strip_prefix("x-")
+        .and_then(|encoded| hex::decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .unwrap_or_else(|| file_stem.to_string())
+}
+
+/// Every account file in the directory, leaving out backups and temporary files
+pub fn list_account_files(config_path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(accounts_dir(config_path)) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.strip_suffix(".json"))
+                .is_some_and(|stem| !stem.is_empty() && !stem.contains('.'))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// Accounts with at least one backup, also the ones whose file is gone
+pub fn list_backed_up_accounts(config_path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(accounts_dir(config_path)) else {
+        return Vec::new();
+    };
+    let mut account_names = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let (file_stem, _index) = file_name.strip_suffix(".json.bak")?.rsplit_once('.')?;
+            Some(account_name_from_stem(file_stem))
+        })
+        .collect::<Vec<_>>();
+    account_names.sort();
+    account_names.dedup();
+    account_names
+}
+
+/// `secret_store` is only needed for accounts whose secrets are kept there
+pub fn read_account_file(
+    path: &Path,
+    secret_store: Option<&dyn SecretStore>,
+) -> Result<StoredAccount, AccountsInitError> {
+    let content = fs::read_to_string(path).map_err(AccountsInitError::IoError)?;
+    parse_account_file(&content, secret_store)
+}
+
+pub fn parse_account_file(
+    content: &str,
+    secret_store: Option<&dyn SecretStore>,
+) -> Result<StoredAccount, AccountsInitError> {
+    let file: AccountFile<&RawValue> =
+        serde_json::from_str(content).map_err(AccountsInitError::DeserializationError)?;
+    match (file.account, file.keyring, file.sealed) {
+        (Some(account), None, None) => {
+            parse_account(account, &file.account_name, file.version).map(StoredAccount::Plain)
+        }
+        (None, Some(account), None) => {
+            let mut account = parse_account(account, &file.account_name, file.version)?;
+            let secret_store = secret_store.ok_or(AccountsInitError::SecretStore(
+                SecretStoreError::Unavailable("No secret store to read from".to_string()),
+            ))?;
+            load_secrets(secret_store, &mut account).map_err(AccountsInitError::SecretStore)?;
+            Ok(StoredAccount::Keyring(account))
+        }
+        (None, None, Some(sealed)) => Ok(StoredAccount::Sealed(SealedAccount {
+            account_name: file.account_name,
+            version: file.version,
+            sealed,
+        })),
+        _ => Err(AccountsInitError::InvalidShape),
+    }
+}
+
+fn parse_account(
+    account: &RawValue,
+    account_name: &str,
+    version: u32,
+) -> Result<UserCredentials, AccountsInitError> {
+    let account = deserialize_account(account.get().as_bytes(), version)?;
+    if account.account_name != account_name {
+        return Err(AccountsInitError::InvalidShape);
+    }
+    Ok(account)
+}
+
+/// Accounts of the current version are read straight into [`UserCredentials`]. Older ones
+/// go through a `Value` to be migrated, which is wiped afterwards.
+fn deserialize_account(json: &[u8], version: u32) -> Result<UserCredentials, AccountsInitError> {
+    if version == CONFIG_VERSION {
+        return serde_json::from_slice(json).map_err(AccountsInitError::DeserializationError);
+    }
+    let mut account: serde_json::Value =
+        serde_json::from_slice(json).map_err(AccountsInitError::DeserializationError)?;
+    let migrated = migrate_account(&mut account, version)
+        .map_err(|err| match err {
+            MigrationError::UnsupportedVersion(version) => {
+                AccountsInitError::UnsupportedVersion(version)
+            }
+            MigrationError::InvalidShape => AccountsInitError::InvalidShape,
+        })
+        .and_then(|()| {
+            UserCredentials::deserialize(&account).map_err(AccountsInitError::DeserializationError)
+        });
+    wipe_json(&mut account);
+    migrated
+}
+
+/// Writes the account with its secrets where `secret_target` says. Returns `false` without
+/// touching the file if it already holds the same account, so a token refresh only rewrites
+/// one file. Moving the secrets out of a plain file deletes its backups.
+pub fn write_account_file(
+    config_path: &Path,
+    account: &UserCredentials,
+    secret_target: SecretTarget,
+) -> io::Result<bool> {
+    let path = account_path(config_path, &account.account_name);
+    let existing = fs::read_to_string(&path).ok().map(Zeroizing::new);
+    let mut file = AccountFile {
+        version: CONFIG_VERSION,
+        account_name: account.account_name.clone(),
+        account: None,
+        keyring: None,
+        sealed: None,
+    };
+    let metadata;
+    match secret_target {
+        SecretTarget::Sealed(vault_key) => {
+            let plaintext = Zeroizing::new(serde_json::to_string(account)?);
+            let unchanged = existing
+                .as_deref()
+                .and_then(|existing| parse_account_file(existing, None).ok())
+                .and_then(|stored| match stored {
+                    StoredAccount::Sealed(sealed) if sealed.version == CONFIG_VERSION => {
+                        vault_key.open(&sealed.sealed).ok()
+                    }
+                    _ => None,
+                })
+                .is_some_and(|opened| opened.as_slice() == plaintext.as_bytes());
+            if unchanged {
+                return Ok(false);
+            }
+            file.sealed = Some(vault_key.seal(plaintext.as_bytes()));
+        }
+        SecretTarget::Keyring(secret_store) => {
+            let mut account = account.clone();
+            let secrets = AccountSecrets::take(&mut account);
+            store_secrets(secret_store, &account.account_name, &secrets)
+                .map_err(|err| io::Error::other(err.to_string()))?;
+            metadata = account;
+            file.keyring = Some(&metadata);
+        }
+        SecretTarget::File => file.account = Some(account),
+    }
+    let content = Zeroizing::new(serde_json::to_string_pretty(&file)?);
+    if existing.as_deref() == Some(&*content) {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(accounts_dir(config_path))?;
+    let hides_plain_secrets = !matches!(secret_target, SecretTarget::File)
+        && existing.as_deref().is_some_and(|existing| {
+            serde_json::from_str::<AccountFile<&RawValue>>(existing)
+                .is_ok_and(|existing| existing.account.is_some())
+        });
+    if hides_plain_secrets {
+        // Older backups would keep the secrets readable
+        remove_backups(&path)?;
+    } else if let Err(err) = rotate_backups(&path, false) {
+        log::warn!("Could not back up {}: {err}", path.display());
+    }
+    write_atomically(&path, content.as_bytes())?;
+    Ok(true)
+}
+
+/// The last version of the file stays behind as its newest backup
+pub fn remove_account_file(path: &Path) -> io::Result<()> {
+    rotate_backups(path, true)?;
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+    use crate::secret_store::cached_store::CachedStore;
+    use crate::secret_store::memory_store::MemoryStore;
+
+    #[test]
+    fn keeps_unusual_account_names_inside_the_directory() {
+        assert_eq!(file_stem("matcha_latte"), "matcha_latte");
+        assert_eq!(file_stem("../config"), "x-2e2e2f636f6e666967");
+        assert_eq!(file_stem(""), "x-");
+        assert_eq!(account_name_from_stem("x-2e2e2f636f6e666967"), "../config");
+        assert_eq!(account_name_from_stem("matcha_latte"), "matcha_latte");
+    }
+
+    #[test]
+    fn skips_unchanged_accounts() {
+        let directory = unique_test_dir("account-files");
+        let config_path = directory.join("config.json");
+        let mut account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+
+        let first_write = write_account_file(&config_path, &account, SecretTarget::File).unwrap();
+        let second_write = write_account_file(&config_path, &account, SecretTarget::File).unwrap();
+        account.access_token = "renewed".into();
+        let third_write = write_account_file(&config_path, &account, SecretTarget::File).unwrap();
+        let files = list_account_files(&config_path);
+        let stored = read_account_file(&files[0], None);
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(first_write && !second_write && third_write);
+        assert_eq!(files.len(), 1);
+        assert!(matches!(
+            stored,
+            Ok(StoredAccount::Plain(stored)) if stored.access_token.expose() == "renewed"
+        ));
+    }
+
+    #[test]
+    fn migrates_older_account_files() {
+        let content = serde_json::json!({
+            "version": 1,
+            "account_name": "matcha_latte",
+            "account": UserCredentials {
+                account_name: "matcha_latte".to_string(),
+                shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+                ..Default::default()
+            },
+        });
+        let mismatched = serde_json::json!({
+            "version": CONFIG_VERSION,
+            "account_name": "espresso",
+            "account": { "account_name": "matcha_latte" },
+        });
+
+        assert!(matches!(
+            parse_account_file(&content.to_string(), None),
+            Ok(StoredAccount::Plain(account))
+                if account.shared_secret.expose() == "FSY2y2mThnpJv1h+lXKTVuH+cvQ="
+        ));
+        assert!(parse_account_file(&mismatched.to_string(), None).is_err());
+    }
+
+    #[test]
+    fn keeps_secrets_out_of_keyring_files() {
+        let directory = unique_test_dir("account-files-keyring");
+        let config_path = directory.join("config.json");
+        let secret_store = MemoryStore::default();
+        let account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            alias: Some("Matcha".to_string()),
+            ..Default::default()
+        };
+
+        write_account_file(&config_path, &account, SecretTarget::Keyring(&secret_store)).unwrap();
+        let path = account_path(&config_path, "matcha_latte");
+        let content = fs::read_to_string(&path).unwrap();
+        let with_store = read_account_file(&path, Some(&secret_store));
+        let without_store = read_account_file(&path, None);
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(!content.contains("FSY2y2mThnpJv1h+lXKTVuH+cvQ=") && content.contains("Matcha"));
+        assert!(matches!(
+            with_store,
+            Ok(StoredAccount::Keyring(stored)) if stored.shared_secret == account.shared_secret
+        ));
+        assert!(matches!(
+            without_store,
+            Err(AccountsInitError::SecretStore(_))
+        ));
+    }
+
+    #[test]
+    fn leaves_the_keyring_alone_for_metadata_changes() {
+        let directory = unique_test_dir("account-files-keyring-metadata");
+        let config_path = directory.join("config.json");
+        let secret_store = CachedStore::new(MemoryStore::default());
+        let mut account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+
+        write_account_file(&config_path, &account, SecretTarget::Keyring(&secret_store)).unwrap();
+        account.tags = vec!["trading".to_string()];
+        let rewritten =
+            write_account_file(&config_path, &account, SecretTarget::Keyring(&secret_store))
+                .unwrap();
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(rewritten);
+        assert_eq!(secret_store.inner().writes(), 1);
+        assert_eq!(secret_store.inner().reads(), 0);
+    }
+}
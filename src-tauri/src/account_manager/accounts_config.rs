@@ -1,36 +1,879 @@
 use std::path::Path;
+use std::sync::Arc;
 use std::{fs, io};
 
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
 
+use super::account_files::{
+    SealedAccount, SecretTarget, StoredAccount, account_name_from_stem, account_path, accounts_dir,
+    list_account_files, parse_account_file, read_account_file, remove_account_file,
+    write_account_file,
+};
+use super::migrations::{CONFIG_VERSION, MigrationError, migrate_accounts, migrate_config};
+use super::payloads::AccountError;
 use crate::auth::user_credentials::UserCredentials;
+use crate::common::atomic_write::{copy_atomically, write_atomically};
+use crate::common::secret::wipe_json;
+use crate::config_backups::payloads::BackupError;
+use crate::config_backups::rotation::{
+    migration_backup_path, remove_backups, remove_migration_backups, rotate_backups,
+};
+use crate::config_recovery::payloads::ConfigLoadError;
+use crate::config_recovery::salvage::scan_objects;
+use crate::secret_store::payloads::SecretStorageError;
+use crate::secret_store::store::{
+    AccountSecrets, SecretStorage, SecretStore, SecretStoreError, store_secrets,
+};
+use crate::vault::crypto::{KdfParams, VaultKey};
+use crate::vault::payloads::VaultError;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Default)]
 pub struct AccountsConfig {
     pub accounts: Vec<UserCredentials>,
     pub active_account_name: Option<String>,
+    /// User defined, an account belongs to at most one of them
+    pub groups: Vec<String>,
+    /// Present while a passkey protected vault is unlocked
+    vault_key: Option<VaultKey>,
+    /// [`VAULT_CHECK`] sealed with `vault_key`, kept so saving does not reseal it every time
+    vault_check: Option<String>,
+    /// Present while a passkey protected vault is still locked
+    locked_vault: Option<LockedVault>,
+    /// Selected by the user, a passkey takes precedence over it
+    secret_storage: SecretStorage,
+    /// The platform secret store. Only written to with [`SecretStorage::Keyring`].
+    secret_store: Option<Arc<dyn SecretStore>>,
+    /// Accounts whose entries in the secret store get deleted on the next save
+    stale_secret_names: Vec<String>,
+    /// Account files that could not be read. They stay on disk untouched.
+    broken_accounts: Vec<BrokenAccount>,
+    /// Accounts whose files get deleted on the next save
+    removed_account_names: Vec<String>,
+    /// Present while the manifest could not be loaded. Saving would destroy it.
+    load_error: Option<ConfigLoadError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenAccount {
+    pub account_name: String,
+    pub error: ConfigLoadError,
+}
+
+/// On-disk shape of `config.json`. The accounts themselves are in `accounts/`, see
+/// [`super::account_files`].
+#[derive(Serialize, Deserialize)]
+struct ManifestFile {
+    /// Missing in files written before versioning, see [`super::migrations`]
+    #[serde(default)]
+    version: u32,
+    /// In the user's order
+    #[serde(default)]
+    accounts: Vec<ManifestEntry>,
+    active_account_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vault: Option<VaultManifest>,
+    #[serde(default)]
+    secret_storage: SecretStorage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    /// Name of an account with its own file
+    File(String),
+    /// Configs before version 2 kept the accounts themselves in the manifest
+    Inline(Box<UserCredentials>),
+}
+
+/// Sealed into the manifest, so a passkey can be checked without any account to open
+const VAULT_CHECK: &[u8] = b"sda-rs vault check";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultManifest {
+    /// Config version `accounts` was sealed with. They can only be migrated once unlocked.
+    #[serde(default)]
+    version: u32,
+    kdf: KdfParams,
+    /// [`VAULT_CHECK`] sealed with the vault key. Missing in vaults written before it existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    check: Option<String>,
+    /// Configs before version 2 sealed all accounts together in here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accounts: Option<String>,
+}
+
+/// What can be read of a vault before the passkey is entered
+#[derive(Debug)]
+struct LockedVault {
+    manifest: VaultManifest,
+    /// In the user's order
+    accounts: Vec<LockedAccount>,
+}
+
+#[derive(Debug)]
+enum LockedAccount {
+    /// Restored from a backup taken before the passkey was set
+    Plain(Box<UserCredentials>),
+    Sealed(SealedAccount),
+}
+
+impl LockedAccount {
+    fn account_name(&self) -> &str {
+        match self {
+            LockedAccount::Plain(account) => &account.account_name,
+            LockedAccount::Sealed(sealed) => &sealed.account_name,
+        }
+    }
+}
+
+/// A parsed manifest before the account files are read
+struct Manifest {
+    accounts_config: AccountsConfig,
+    account_names: Vec<String>,
+    /// Version of the file before migrating
+    version: u32,
 }
 
 #[derive(Debug)]
 pub enum AccountsInitError {
     IoError(io::Error),
-    DeserializationError,
+    DeserializationError(serde_json::Error),
+    /// Valid JSON, but not laid out the way the migrations expect
+    InvalidShape,
+    /// The file was written by a newer version of the app
+    UnsupportedVersion(u32),
+    /// The account file is fine, its secrets could not be read
+    SecretStore(SecretStoreError),
 }
 
 impl AccountsConfig {
-    pub fn from_config(config_path: &Path) -> Result<Self, AccountsInitError> {
+    /// Reads the manifest and every account file. Broken account files only take out
+    /// their own account, see [`Self::broken_accounts`].
+    pub fn from_config(
+        config_path: &Path,
+        secret_store: Arc<dyn SecretStore>,
+    ) -> Result<Self, AccountsInitError> {
         if !config_path.exists() {
-            return Ok(Default::default());
+            // Account files without a manifest are still picked up
+            let mut accounts_config = Self {
+                secret_store: Some(secret_store),
+                ..Default::default()
+            };
+            accounts_config.load_account_files(config_path, &[]);
+            return Ok(accounts_config);
         }
         let json_content = fs::read_to_string(config_path).map_err(AccountsInitError::IoError)?;
-        serde_json::from_str(&json_content).map_err(|_| AccountsInitError::DeserializationError)
+        let mut manifest = Self::parse(&json_content)?;
+        if manifest.version < CONFIG_VERSION {
+            // The migrated config only reaches the disk on the next save
+            let version = manifest.version;
+            let backup_path = migration_backup_path(config_path, version);
+            copy_atomically(config_path, &backup_path).map_err(AccountsInitError::IoError)?;
+            log::info!(
+                "Migrating the config from version {version} to {CONFIG_VERSION}, backed up to {}",
+                backup_path.display()
+            );
+        }
+        manifest.accounts_config.secret_store = Some(secret_store);
+        manifest
+            .accounts_config
+            .load_account_files(config_path, &manifest.account_names);
+        Ok(manifest.accounts_config)
+    }
+
+    /// Checks the content of a `config.json` without reading any account file
+    pub fn validate_manifest(json_content: &str) -> Result<(), AccountsInitError> {
+        Self::parse(json_content).map(|_| ())
+    }
+
+    fn parse(json_content: &str) -> Result<Manifest, AccountsInitError> {
+        let mut json: serde_json::Value =
+            serde_json::from_str(json_content).map_err(AccountsInitError::DeserializationError)?;
+        let version = migrate_config(&mut json).map_err(|err| match err {
+            MigrationError::UnsupportedVersion(version) => {
+                AccountsInitError::UnsupportedVersion(version)
+            }
+            MigrationError::InvalidShape => AccountsInitError::InvalidShape,
+        })?;
+        let file: ManifestFile = serde_json::from_value(json).map_err(|err| {
+            // Errors from a `Value` have no position, the raw text usually fails at the same spot
+            let positioned = serde_json::from_str::<ManifestFile>(json_content).err();
+            AccountsInitError::DeserializationError(positioned.unwrap_or(err))
+        })?;
+
+        let mut accounts_config = Self {
+            active_account_name: file.active_account_name,
+            groups: file.groups,
+            locked_vault: file.vault.map(|manifest| LockedVault {
+                manifest,
+                accounts: Vec::new(),
+            }),
+            secret_storage: file.secret_storage,
+            ..Default::default()
+        };
+        let mut account_names = Vec::new();
+        for entry in file.accounts {
+            match entry {
+                ManifestEntry::File(account_name) => account_names.push(account_name),
+                ManifestEntry::Inline(account) => {
+                    accounts_config.add_stored_account(StoredAccount::Plain(*account))
+                }
+            }
+        }
+        Ok(Manifest {
+            accounts_config,
+            account_names,
+            version,
+        })
+    }
+
+    /// Reads the listed accounts, then any file the manifest does not know about. Those are
+    /// left behind when the app stops between writing an account and the manifest.
+    fn load_account_files(&mut self, config_path: &Path, account_names: &[String]) {
+        let listed = account_names
+            .iter()
+            .map(|account_name| {
+                (
+                    account_name.clone(),
+                    account_path(config_path, account_name),
+                )
+            })
+            .collect::<Vec<_>>();
+        let unlisted = list_account_files(config_path)
+            .into_iter()
+            .filter(|path| !listed.iter().any(|(_, listed_path)| listed_path == path))
+            .map(|path| {
+                let file_stem = path.file_stem().unwrap_or_default();
+                (account_name_from_stem(&file_stem.to_string_lossy()), path)
+            })
+            .collect::<Vec<_>>();
+
+        for (account_name, path) in listed.into_iter().chain(unlisted) {
+            match read_account_file(&path, self.secret_store.as_deref()) {
+                Ok(stored) => self.add_stored_account(stored),
+                Err(err) => {
+                    log::error!(
+                        "Could not read the account file {}: {err:?}",
+                        path.display()
+                    );
+                    self.broken_accounts.push(BrokenAccount {
+                        account_name,
+                        error: (&err).into(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Ignores accounts that are already known. An account whose secrets are in the secret
+    /// store keeps the install on that storage, even if the manifest was lost.
+    fn add_stored_account(&mut self, stored: StoredAccount) {
+        let account_name = match &stored {
+            StoredAccount::Plain(account) | StoredAccount::Keyring(account) => {
+                account.account_name.clone()
+            }
+            StoredAccount::Sealed(sealed) => sealed.account_name.clone(),
+        };
+        if self.stored_account_names().contains(&account_name) {
+            return;
+        }
+        if matches!(stored, StoredAccount::Keyring(_)) && !self.is_encrypted() {
+            self.secret_storage = SecretStorage::Keyring;
+        }
+        match (&mut self.locked_vault, stored) {
+            (
+                Some(locked_vault),
+                StoredAccount::Plain(account) | StoredAccount::Keyring(account),
+            ) => locked_vault
+                .accounts
+                .push(LockedAccount::Plain(Box::new(account))),
+            (Some(locked_vault), StoredAccount::Sealed(sealed)) => {
+                locked_vault.accounts.push(LockedAccount::Sealed(sealed))
+            }
+            (None, StoredAccount::Plain(account) | StoredAccount::Keyring(account)) => {
+                self.accounts.push(account)
+            }
+            (None, StoredAccount::Sealed(_)) => self.broken_accounts.push(BrokenAccount {
+                account_name,
+                error: ConfigLoadError {
+                    message: "The account is sealed, but the config has no passkey".to_string(),
+                    line: None,
+                    column: None,
+                },
+            }),
+        }
+    }
+
+    /// Names of the readable accounts, also while the vault is locked
+    pub fn stored_account_names(&self) -> Vec<String> {
+        match &self.locked_vault {
+            Some(locked_vault) => locked_vault
+                .accounts
+                .iter()
+                .map(|account| account.account_name().to_string())
+                .collect(),
+            None => self
+                .accounts
+                .iter()
+                .map(|account| account.account_name.clone())
+                .collect(),
+        }
+    }
+
+    pub fn broken_accounts(&self) -> &[BrokenAccount] {
+        &self.broken_accounts
+    }
+
+    /// Empty config standing in for one that could not be loaded. It refuses to be saved
+    /// until it gets replaced by a restored or salvaged one.
+    pub fn recovering(load_error: ConfigLoadError) -> Self {
+        Self {
+            load_error: Some(load_error),
+            ..Default::default()
+        }
+    }
+
+    /// Picks the accounts, or the vault, that still parse out of a broken manifest and adds
+    /// the account files next to it. The first salvaged account becomes the active one.
+    pub fn salvage(
+        config_path: &Path,
+        json_content: &str,
+        secret_store: Arc<dyn SecretStore>,
+    ) -> Self {
+        let mut salvaged = Self {
+            locked_vault: scan_objects::<VaultManifest>(json_content)
+                .into_iter()
+                .next()
+                .map(|manifest| LockedVault {
+                    manifest,
+                    accounts: Vec::new(),
+                }),
+            secret_store: Some(secret_store),
+            ..Default::default()
+        };
+        for account in scan_objects::<UserCredentials>(json_content) {
+            salvaged.add_stored_account(StoredAccount::Plain(account));
+        }
+        salvaged.load_account_files(config_path, &[]);
+        salvaged.active_account_name = salvaged.stored_account_names().into_iter().next();
+        salvaged
+    }
+
+    pub fn load_error(&self) -> Option<&ConfigLoadError> {
+        self.load_error.as_ref()
     }
 
+    /// Writes the changed accounts, sealed with the vault key if a passkey is set or with
+    /// their secrets in the secret store, then the manifest. A locked vault keeps its files
+    /// as they are. Refused in recovery mode.
     pub fn save_to_config(&self, config_path: &Path) -> io::Result<()> {
+        if self.load_error.is_some() {
+            return Err(io::Error::other(
+                "The config could not be loaded, restore or salvage it first",
+            ));
+        }
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(config_path, serde_json::to_string_pretty(&self).unwrap())
+
+        // Accounts go first. If the app stops before the manifest is written, the new
+        // files are picked up as unlisted ones on the next start.
+        if self.locked_vault.is_none() {
+            let secret_target = match (&self.vault_key, self.secret_storage, &self.secret_store) {
+                (Some(vault_key), _, _) => SecretTarget::Sealed(vault_key),
+                (None, SecretStorage::Keyring, Some(secret_store)) => {
+                    SecretTarget::Keyring(secret_store.as_ref())
+                }
+                (None, SecretStorage::Keyring, None) => {
+                    return Err(io::Error::other("There is no secret store to save to"));
+                }
+                (None, SecretStorage::File, _) => SecretTarget::File,
+            };
+            for account in &self.accounts {
+                write_account_file(config_path, account, secret_target)?;
+            }
+        }
+        let mut account_names = self.stored_account_names();
+        for broken_account in &self.broken_accounts {
+            if !account_names.contains(&broken_account.account_name) {
+                account_names.push(broken_account.account_name.clone());
+            }
+        }
+        let vault = match (&self.locked_vault, &self.vault_key) {
+            (Some(locked_vault), _) => Some(locked_vault.manifest.clone()),
+            (None, Some(vault_key)) => Some(VaultManifest {
+                version: CONFIG_VERSION,
+                kdf: vault_key.params().clone(),
+                check: self.vault_check.clone(),
+                accounts: None,
+            }),
+            (None, None) => None,
+        };
+        let manifest = ManifestFile {
+            version: CONFIG_VERSION,
+            accounts: account_names.into_iter().map(ManifestEntry::File).collect(),
+            active_account_name: self.active_account_name.clone(),
+            groups: self.groups.clone(),
+            vault,
+            secret_storage: self.secret_storage,
+        };
+        let content = serde_json::to_string_pretty(&manifest)?;
+        if fs::read_to_string(config_path).ok().as_ref() != Some(&content) {
+            if let Err(err) = rotate_backups(config_path, false) {
+                // A missing backup should not stop tokens from being saved
+                log::warn!("Could not back up the config: {err}");
+            }
+            write_atomically(config_path, content.as_bytes())?;
+        }
+
+        for account_name in &self.removed_account_names {
+            let path = account_path(config_path, account_name);
+            let is_stored = self.stored_account_names().contains(account_name);
+            if !is_stored && path.exists() {
+                remove_account_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts an account file back from a backup, also over a broken one.
+    /// The caller is responsible for saving, so the manifest lists it again.
+    pub fn restore_account(
+        &mut self,
+        config_path: &Path,
+        account_name: &str,
+        content: &str,
+    ) -> Result<(), BackupError> {
+        let locked_account = match parse_account_file(content, self.secret_store.as_deref())? {
+            StoredAccount::Plain(account) | StoredAccount::Keyring(account) => {
+                LockedAccount::Plain(Box::new(account))
+            }
+            StoredAccount::Sealed(sealed) => LockedAccount::Sealed(sealed),
+        };
+        if locked_account.account_name() != account_name {
+            return Err(BackupError::Corrupted);
+        }
+        // Sealed with another passkey, or the vault is gone since
+        let opened = match (&locked_account, &self.locked_vault, &self.vault_key) {
+            (LockedAccount::Plain(account), None, _) => Some(UserCredentials::clone(account)),
+            (LockedAccount::Sealed(sealed), None, Some(vault_key)) => {
+                Some(sealed.open(vault_key).map_err(|_| BackupError::Corrupted)?)
+            }
+            (LockedAccount::Sealed(_), None, None) => return Err(BackupError::Corrupted),
+            (_, Some(_), _) => None,
+        };
+
+        let path = account_path(config_path, account_name);
+        fs::create_dir_all(accounts_dir(config_path))
+            .and_then(|()| rotate_backups(&path, true))
+            .and_then(|()| write_atomically(&path, content.as_bytes()))
+            .map_err(|err| BackupError::IOError(err.to_string()))?;
+
+        match (&mut self.locked_vault, opened) {
+            (Some(locked_vault), _) => {
+                locked_vault
+                    .accounts
+                    .retain(|account| account.account_name() != account_name);
+                locked_vault.accounts.push(locked_account);
+            }
+            (None, Some(account)) => match self.find_account_mut(account_name) {
+                Some(existing) => *existing = account,
+                None => self.accounts.push(account),
+            },
+            (None, None) => {}
+        }
+        self.broken_accounts
+            .retain(|broken_account| broken_account.account_name != account_name);
+        self.removed_account_names
+            .retain(|removed_name| removed_name != account_name);
+        Ok(())
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.vault_key.is_some() || self.locked_vault.is_some()
+    }
+
+    /// Present while a passkey protected vault is unlocked
+    pub fn vault_key(&self) -> Option<&VaultKey> {
+        self.vault_key.as_ref()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked_vault.is_some()
+    }
+
+    /// Decrypts the accounts read from disk. Argon2 makes this intentionally slow.
+    /// The passkey is checked against the manifest, an account that fails to open only
+    /// takes out itself.
+    pub fn unlock(&mut self, passkey: &str) -> Result<(), VaultError> {
+        let locked_vault = self.locked_vault.as_ref().ok_or(VaultError::NotEncrypted)?;
+        let vault_key = VaultKey::derive(passkey, locked_vault.manifest.kdf.clone())?;
+        let verified = match &locked_vault.manifest.check {
+            Some(check) => {
+                if *vault_key.open(check)? != VAULT_CHECK {
+                    return Err(VaultError::WrongPasskey);
+                }
+                true
+            }
+            None => false,
+        };
+
+        let mut accounts = Vec::new();
+        if let Some(sealed_accounts) = &locked_vault.manifest.accounts {
+            let plaintext = vault_key.open(sealed_accounts)?;
+            let mut legacy_accounts: serde_json::Value =
+                serde_json::from_slice(&plaintext).map_err(|_| VaultError::Corrupted)?;
+            let migrated = migrate_accounts(&mut legacy_accounts, locked_vault.manifest.version)
+                .ok()
+                .and_then(|()| Vec::<UserCredentials>::deserialize(&legacy_accounts).ok());
+            wipe_json(&mut legacy_accounts);
+            accounts = migrated.ok_or(VaultError::Corrupted)?;
+        }
+        let mut opened_any = verified || locked_vault.manifest.accounts.is_some();
+        let mut broken_accounts = Vec::new();
+        for locked_account in &locked_vault.accounts {
+            match locked_account {
+                LockedAccount::Plain(account) => accounts.push(UserCredentials::clone(account)),
+                LockedAccount::Sealed(sealed) => match sealed.open(&vault_key) {
+                    Ok(account) => {
+                        accounts.push(account);
+                        opened_any = true;
+                    }
+                    Err(err) => broken_accounts.push(BrokenAccount {
+                        account_name: sealed.account_name.clone(),
+                        error: ConfigLoadError {
+                            message: format!("The sealed account could not be opened: {err:?}"),
+                            line: None,
+                            column: None,
+                        },
+                    }),
+                },
+            }
+        }
+
+        // Without a check value, a vault none of whose accounts open can only be the wrong passkey
+        if !opened_any && !broken_accounts.is_empty() {
+            return Err(VaultError::WrongPasskey);
+        }
+
+        self.accounts = accounts;
+        self.broken_accounts.extend(broken_accounts);
+        self.vault_check = Some(
+            locked_vault
+                .manifest
+                .check
+                .clone()
+                .unwrap_or_else(|| vault_key.seal(VAULT_CHECK)),
+        );
+        self.vault_key = Some(vault_key);
+        self.locked_vault = None;
+        Ok(())
+    }
+
+    /// Seals the unlocked accounts again, wipes their secrets and forgets the vault key, so
+    /// nothing works until the passkey is entered. Save first, a locked vault does not write
+    /// its accounts. Returns `false` without a passkey or while already locked.
+    pub fn lock(&mut self) -> bool {
+        let Some(vault_key) = self.vault_key.take() else {
+            return false;
+        };
+        let accounts = std::mem::take(&mut self.accounts)
+            .into_iter()
+            .map(|mut account| {
+                let plaintext = Zeroizing::new(
+                    serde_json::to_vec(&account).expect("Accounts always serialize"),
+                );
+                drop(AccountSecrets::take(&mut account));
+                LockedAccount::Sealed(SealedAccount {
+                    account_name: account.account_name,
+                    version: CONFIG_VERSION,
+                    sealed: vault_key.seal(&plaintext),
+                })
+            })
+            .collect();
+        self.locked_vault = Some(LockedVault {
+            manifest: VaultManifest {
+                version: CONFIG_VERSION,
+                kdf: vault_key.params().clone(),
+                check: self.vault_check.take(),
+                accounts: None,
+            },
+            accounts,
+        });
+        true
+    }
+
+    /// Sets, replaces or (with `None`) removes the passkey. The caller is responsible for saving.
+    pub fn set_passkey(&mut self, passkey: Option<&str>) -> Result<(), VaultError> {
+        if self.is_locked() {
+            return Err(VaultError::Locked);
+        }
+        if passkey.is_some() && self.secret_storage == SecretStorage::Keyring {
+            return Err(VaultError::SecretStoreInUse);
+        }
+        self.vault_key = passkey
+            .map(|passkey| VaultKey::derive(passkey, KdfParams::generate()))
+            .transpose()?;
+        self.vault_check = self
+            .vault_key
+            .as_ref()
+            .map(|vault_key| vault_key.seal(VAULT_CHECK));
+        Ok(())
+    }
+
+    pub fn verify_passkey(&self, passkey: &str) -> Result<(), VaultError> {
+        match &self.vault_key {
+            Some(vault_key) if vault_key.matches(passkey) => Ok(()),
+            Some(_) => Err(VaultError::WrongPasskey),
+            None if self.is_locked() => Err(VaultError::Locked),
+            None => Err(VaultError::NotEncrypted),
+        }
+    }
+
+    pub fn secret_storage(&self) -> SecretStorage {
+        self.secret_storage
+    }
+
+    /// The secret store is only an alternative to a passkey. Moving into it writes the
+    /// secrets right away, so an unavailable store fails before anything changes. Moving
+    /// out of it deletes them after the next save. The caller is responsible for saving.
+    pub fn set_secret_storage(
+        &mut self,
+        secret_storage: SecretStorage,
+    ) -> Result<(), SecretStorageError> {
+        if self.load_error.is_some() {
+            return Err(SecretStorageError::Recovering);
+        }
+        if self.is_locked() {
+            return Err(SecretStorageError::Locked);
+        }
+        if secret_storage == SecretStorage::Keyring && self.is_encrypted() {
+            return Err(SecretStorageError::PasskeyEnabled);
+        }
+        if secret_storage == self.secret_storage {
+            return Ok(());
+        }
+        let secret_store = self
+            .secret_store
+            .clone()
+            .ok_or(SecretStoreError::Unavailable(
+                "No secret store on this platform".to_string(),
+            ))?;
+
+        match secret_storage {
+            SecretStorage::Keyring => {
+                for account in &self.accounts {
+                    let mut metadata = account.clone();
+                    let secrets = AccountSecrets::take(&mut metadata);
+                    store_secrets(secret_store.as_ref(), &account.account_name, &secrets)?;
+                }
+                self.stale_secret_names.clear();
+            }
+            SecretStorage::File => self.stale_secret_names = self.stored_account_names(),
+        }
+        self.secret_storage = secret_storage;
+        Ok(())
+    }
+
+    /// Deletes the secrets of removed accounts, or of all accounts after moving out of the
+    /// secret store. Only call this once the account files are saved without them.
+    pub fn delete_stale_secrets(&mut self) {
+        let Some(secret_store) = &self.secret_store else {
+            return;
+        };
+        let stored_account_names = self.stored_account_names();
+        for account_name in std::mem::take(&mut self.stale_secret_names) {
+            // Added again since it was removed
+            if self.secret_storage == SecretStorage::Keyring
+                && stored_account_names.contains(&account_name)
+            {
+                continue;
+            }
+            if let Err(err) = secret_store.delete(&account_name) {
+                log::warn!("Could not delete the secrets of {account_name}: {err}");
+            }
+        }
+    }
+
+    /// Deletes the backups of the manifest, its migrations and the accounts. Those taken before
+    /// a passkey or the secret store was turned on still hold the secrets in plaintext.
+    /// Only call this once the account files are saved without them.
+    pub fn remove_plain_backups(&self, config_path: &Path) -> io::Result<()> {
+        remove_backups(config_path)?;
+        remove_migration_backups(config_path)?;
+        for account_name in self.stored_account_names() {
+            remove_backups(&account_path(config_path, &account_name))?;
+        }
+        Ok(())
+    }
+
+    /// Adds the account or replaces the one with the same name, returning whether it was replaced.
+    /// Keeps the password, tokens and user settings of the existing account the import lacks.
+    pub fn import_account(&mut self, mut account: UserCredentials) -> bool {
+        let existing = self
+            .accounts
+            .iter_mut()
+            .find(|existing| existing.account_name == account.account_name);
+        match existing {
+            Some(existing) => {
+                if account.account_password.is_empty() {
+                    account.account_password = std::mem::take(&mut existing.account_password);
+                }
+                if account.access_token.is_empty() || account.refresh_token.is_empty() {
+                    account.access_token = std::mem::take(&mut existing.access_token);
+                    account.refresh_token = std::mem::take(&mut existing.refresh_token);
+                }
+                if account.tags.is_empty() {
+                    account.tags = std::mem::take(&mut existing.tags);
+                }
+                if account.alias.is_none() {
+                    account.alias = existing.alias.take();
+                }
+                if account.group.is_none() {
+                    account.group = existing.group.take();
+                }
+                account.favourite |= existing.favourite;
+                *existing = account;
+                true
+            }
+            None => {
+                self.accounts.push(account);
+                false
+            }
+        }
+    }
+
+    /// Copies renewed tokens into the stored account, returning whether it still exists.
+    /// The caller is responsible for saving.
+    pub fn store_tokens(&mut self, renewed: &UserCredentials) -> bool {
+        match self
+            .accounts
+            .iter_mut()
+            .find(|account| account.account_name == renewed.account_name)
+        {
+            Some(account) => {
+                account.access_token = renewed.access_token.clone();
+                account.refresh_token = renewed.refresh_token.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn find_account(&self, account_name: &str) -> Option<&UserCredentials> {
+        self.accounts
+            .iter()
+            .find(|account| account.account_name == account_name)
+    }
+
+    pub fn find_account_mut(&mut self, account_name: &str) -> Option<&mut UserCredentials> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.account_name == account_name)
+    }
+
+    /// Returns whether the account exists. The caller is responsible for saving.
+    pub fn set_active_account(&mut self, account_name: &str) -> bool {
+        let exists = self
+            .accounts
+            .iter()
+            .any(|account| account.account_name == account_name);
+        if exists {
+            self.active_account_name = Some(account_name.to_string());
+        }
+        exists
+    }
+
+    /// Removes the account, leaving no active account if it was the active one.
+    /// The caller is responsible for saving, which deletes the account file.
+    pub fn remove_account(&mut self, account_name: &str) -> Option<UserCredentials> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|account| account.account_name == account_name)?;
+        if self.active_account_name.as_deref() == Some(account_name) {
+            self.active_account_name = None;
+        }
+        self.removed_account_names.push(account_name.to_string());
+        if self.secret_storage == SecretStorage::Keyring {
+            self.stale_secret_names.push(account_name.to_string());
+        }
+        Some(self.accounts.remove(index))
+    }
+
+    pub fn create_group(&mut self, name: &str) -> Result<(), AccountError> {
+        if self.groups.iter().any(|group| group == name) {
+            return Err(AccountError::GroupAlreadyExists);
+        }
+        self.groups.push(name.to_string());
+        Ok(())
+    }
+
+    /// Moves the accounts of the group along with it. Renaming to the same name does nothing.
+    pub fn rename_group(&mut self, name: &str, new_name: &str) -> Result<(), AccountError> {
+        if name != new_name && self.groups.iter().any(|group| group == new_name) {
+            return Err(AccountError::GroupAlreadyExists);
+        }
+        let group = self
+            .groups
+            .iter_mut()
+            .find(|group| *group == name)
+            .ok_or(AccountError::GroupNotFound)?;
+        *group = new_name.to_string();
+        for account in &mut self.accounts {
+            if account.group.as_deref() == Some(name) {
+                account.group = Some(new_name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The accounts of the group are kept, just without a group
+    pub fn delete_group(&mut self, name: &str) -> Result<(), AccountError> {
+        let index = self
+            .groups
+            .iter()
+            .position(|group| group == name)
+            .ok_or(AccountError::GroupNotFound)?;
+        self.groups.remove(index);
+        for account in &mut self.accounts {
+            if account.group.as_deref() == Some(name) {
+                account.group = None;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_account_group(
+        &mut self,
+        account_name: &str,
+        group: Option<String>,
+    ) -> Result<(), AccountError> {
+        if let Some(group) = &group
+            && !self.groups.contains(group)
+        {
+            return Err(AccountError::GroupNotFound);
+        }
+        let account = self
+            .find_account_mut(account_name)
+            .ok_or(AccountError::AccountNotFound)?;
+        account.group = group;
+        Ok(())
+    }
+
+    /// Puts the accounts in the given order. Accounts left out keep their relative order
+    /// after the listed ones.
+    pub fn reorder_accounts(&mut self, account_names: &[String]) {
+        self.accounts.sort_by_key(|account| {
+            account_names
+                .iter()
+                .position(|account_name| *account_name == account.account_name)
+                .unwrap_or(account_names.len())
+        });
     }
 
     pub fn get_active_account(&self) -> Option<&UserCredentials> {
@@ -54,3 +897,263 @@ impl AccountsConfig {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+    use crate::secret_store::memory_store::MemoryStore;
+
+    fn accounts_config(account_names: &[&str], active_account_name: &str) -> AccountsConfig {
+        AccountsConfig {
+            accounts: account_names
+                .iter()
+                .map(|account_name| UserCredentials {
+                    account_name: account_name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            active_account_name: Some(active_account_name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn switches_only_to_known_accounts() {
+        let mut config = accounts_config(&["matcha_latte", "espresso"], "matcha_latte");
+        assert!(config.set_active_account("espresso"));
+        assert!(!config.set_active_account("cappuccino"));
+        assert_eq!(config.active_account_name.as_deref(), Some("espresso"));
+    }
+
+    #[test]
+    fn removing_the_active_account_clears_it() {
+        let mut config = accounts_config(&["matcha_latte", "espresso"], "matcha_latte");
+        assert!(config.remove_account("matcha_latte").is_some());
+        assert_eq!(config.active_account_name, None);
+        assert_eq!(config.accounts.len(), 1);
+    }
+
+    #[test]
+    fn removing_another_account_keeps_the_active_one() {
+        let mut config = accounts_config(&["matcha_latte", "espresso"], "matcha_latte");
+        assert!(config.remove_account("espresso").is_some());
+        assert!(config.remove_account("espresso").is_none());
+        assert_eq!(config.active_account_name.as_deref(), Some("matcha_latte"));
+    }
+
+    #[test]
+    fn renaming_a_group_moves_its_accounts() {
+        let mut config = accounts_config(&["matcha_latte", "espresso"], "matcha_latte");
+        config.create_group("trading").unwrap();
+        config.create_group("market").unwrap();
+        config
+            .set_account_group("matcha_latte", Some("trading".to_string()))
+            .unwrap();
+
+        assert!(matches!(
+            config.rename_group("trading", "market"),
+            Err(AccountError::GroupAlreadyExists)
+        ));
+        config.rename_group("trading", "trading").unwrap();
+        config.rename_group("trading", "team trading").unwrap();
+        assert_eq!(config.groups, ["team trading", "market"]);
+        assert_eq!(config.accounts[0].group.as_deref(), Some("team trading"));
+    }
+
+    #[test]
+    fn deleting_a_group_keeps_its_accounts() {
+        let mut config = accounts_config(&["matcha_latte"], "matcha_latte");
+        config.create_group("test").unwrap();
+        config
+            .set_account_group("matcha_latte", Some("test".to_string()))
+            .unwrap();
+        config.delete_group("test").unwrap();
+        assert!(config.groups.is_empty());
+        assert_eq!(config.accounts.len(), 1);
+        assert_eq!(config.accounts[0].group, None);
+    }
+
+    #[test]
+    fn rejects_unknown_group() {
+        let mut config = accounts_config(&["matcha_latte"], "matcha_latte");
+        assert!(matches!(
+            config.set_account_group("matcha_latte", Some("trading".to_string())),
+            Err(AccountError::GroupNotFound)
+        ));
+    }
+
+    #[test]
+    fn reorders_listed_accounts_first() {
+        let mut config = accounts_config(&["a", "b", "c", "d"], "a");
+        config.reorder_accounts(&["c".to_string(), "a".to_string()]);
+        let account_names = config
+            .accounts
+            .iter()
+            .map(|account| account.account_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(account_names, ["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn reimport_keeps_user_settings() {
+        let mut config = accounts_config(&["matcha_latte"], "matcha_latte");
+        config.accounts[0].alias = Some("Main".to_string());
+        config.accounts[0].tags = vec!["trading".to_string()];
+        let replaced = config.import_account(UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            ..Default::default()
+        });
+        assert!(replaced);
+        assert_eq!(config.accounts[0].alias.as_deref(), Some("Main"));
+        assert_eq!(config.accounts[0].tags, ["trading"]);
+    }
+
+    #[test]
+    fn moves_secrets_into_the_secret_store_and_back() {
+        let directory = unique_test_dir("secret-storage");
+        let config_path = directory.join("config.json");
+        let account_path = account_path(&config_path, "matcha_latte");
+        let secret_store = Arc::new(MemoryStore::default());
+        let mut config = AccountsConfig::from_config(&config_path, secret_store.clone()).unwrap();
+        config.accounts.push(UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        });
+
+        config.set_secret_storage(SecretStorage::Keyring).unwrap();
+        config.save_to_config(&config_path).unwrap();
+        let keyring_file = fs::read_to_string(&account_path).unwrap();
+        let mut reloaded = AccountsConfig::from_config(&config_path, secret_store.clone()).unwrap();
+        let passkey_result = reloaded.set_passkey(Some("correct horse"));
+        let reloaded_secret = reloaded.accounts[0].shared_secret.clone();
+        reloaded.set_secret_storage(SecretStorage::File).unwrap();
+        reloaded.save_to_config(&config_path).unwrap();
+        reloaded.delete_stale_secrets();
+        let plain_file = fs::read_to_string(&account_path).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(!keyring_file.contains("FSY2y2mThnpJv1h+lXKTVuH+cvQ="));
+        assert!(matches!(passkey_result, Err(VaultError::SecretStoreInUse)));
+        assert_eq!(reloaded_secret.expose(), "FSY2y2mThnpJv1h+lXKTVuH+cvQ=");
+        assert!(plain_file.contains("FSY2y2mThnpJv1h+lXKTVuH+cvQ="));
+        assert!(!secret_store.contains("matcha_latte"));
+    }
+
+    #[test]
+    fn keeps_secrets_in_files_when_the_secret_store_is_unavailable() {
+        let mut config = AccountsConfig {
+            secret_store: Some(Arc::new(MemoryStore::unavailable())),
+            ..accounts_config(&["matcha_latte"], "matcha_latte")
+        };
+        let result = config.set_secret_storage(SecretStorage::Keyring);
+        assert!(matches!(result, Err(SecretStorageError::Unavailable(_))));
+        assert_eq!(config.secret_storage(), SecretStorage::File);
+    }
+
+    #[test]
+    fn locking_needs_the_passkey_again() {
+        let mut config = accounts_config(&["matcha_latte"], "matcha_latte");
+        config.accounts[0].shared_secret = "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into();
+        assert!(!config.lock());
+
+        config.set_passkey(Some("correct horse")).unwrap();
+        assert!(config.lock());
+        assert!(config.is_locked() && config.accounts.is_empty());
+        assert_eq!(config.stored_account_names(), ["matcha_latte"]);
+
+        config.unlock("correct horse").unwrap();
+        assert_eq!(
+            config.get_active_account().unwrap().shared_secret.expose(),
+            "FSY2y2mThnpJv1h+lXKTVuH+cvQ="
+        );
+    }
+
+    #[test]
+    fn empty_vault_rejects_a_wrong_passkey() {
+        let mut config = accounts_config(&[], "matcha_latte");
+        config.set_passkey(Some("correct horse")).unwrap();
+        assert!(config.lock());
+
+        assert!(matches!(
+            config.unlock("battery staple"),
+            Err(VaultError::WrongPasskey)
+        ));
+        config.unlock("correct horse").unwrap();
+        assert!(!config.is_locked());
+    }
+
+    #[test]
+    fn corrupt_account_does_not_reject_the_passkey() {
+        let directory = unique_test_dir("vault-corrupt-account");
+        let config_path = directory.join("config.json");
+        let secret_store = Arc::new(MemoryStore::default());
+        let mut config = accounts_config(&["espresso", "matcha_latte"], "matcha_latte");
+        config.set_passkey(Some("correct horse")).unwrap();
+        config.save_to_config(&config_path).unwrap();
+        let first_path = account_path(&config_path, "espresso");
+        let corrupted = fs::read_to_string(&first_path)
+            .unwrap()
+            .replace("\"sealed\": \"", "\"sealed\": \"AAAA");
+        fs::write(&first_path, corrupted).unwrap();
+
+        let mut reloaded = AccountsConfig::from_config(&config_path, secret_store.clone()).unwrap();
+        let wrong_result = reloaded.unlock("battery staple");
+        let result = reloaded.unlock("correct horse");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(matches!(wrong_result, Err(VaultError::WrongPasskey)));
+        assert!(result.is_ok());
+        assert_eq!(reloaded.accounts.len(), 1);
+        assert_eq!(reloaded.accounts[0].account_name, "matcha_latte");
+        assert_eq!(reloaded.broken_accounts()[0].account_name, "espresso");
+    }
+
+    #[test]
+    fn enabling_a_passkey_leaves_no_plaintext_secret() {
+        fn files_in(directory: &Path) -> Vec<std::path::PathBuf> {
+            fs::read_dir(directory)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .flat_map(|path| match path.is_dir() {
+                    true => files_in(&path),
+                    false => vec![path],
+                })
+                .collect()
+        }
+
+        let directory = unique_test_dir("plain-backups");
+        let config_path = directory.join("config.json");
+        let account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+        let legacy_config = serde_json::json!({
+            "version": 1,
+            "accounts": [account],
+            "active_account_name": "matcha_latte",
+        });
+        fs::write(&config_path, legacy_config.to_string()).unwrap();
+        let secret_store = Arc::new(MemoryStore::default());
+        let mut config = AccountsConfig::from_config(&config_path, secret_store).unwrap();
+        config.save_to_config(&config_path).unwrap();
+        config.save_to_config(&config_path).unwrap();
+
+        config.set_passkey(Some("correct horse")).unwrap();
+        config.save_to_config(&config_path).unwrap();
+        config.remove_plain_backups(&config_path).unwrap();
+        let leaked = files_in(&directory)
+            .into_iter()
+            .filter(|path| {
+                fs::read_to_string(path)
+                    .unwrap()
+                    .contains("FSY2y2mThnpJv1h+lXKTVuH+cvQ=")
+            })
+            .collect::<Vec<_>>();
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(leaked.is_empty(), "{leaked:?}");
+    }
+}
@@ -1,22 +1,34 @@
-use std::sync::MutexGuard;
-
 use serde::Serialize;
 
-use crate::account_manager::accounts_config::AccountsConfig;
+use crate::account_manager::accounts_config::{AccountsConfig, BrokenAccount};
 use crate::auth::user_credentials::UserCredentials;
+use crate::avatars::cache::AvatarCache;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountResponse {
     username: String,
+    alias: Option<String>,
+    /// Local path of the cached picture
     avatar_url: Option<String>,
+    has_authenticator: bool,
+    group: Option<String>,
+    tags: Vec<String>,
+    favourite: bool,
 }
 
-impl From<&UserCredentials> for AccountResponse {
-    fn from(value: &UserCredentials) -> Self {
+impl AccountResponse {
+    pub fn new(value: &UserCredentials, avatar_cache: &AvatarCache) -> Self {
         Self {
             username: value.account_name.clone(),
-            avatar_url: None,
+            alias: value.alias.clone(),
+            avatar_url: avatar_cache
+                .path(value.steam_id)
+                .map(|path| path.to_string_lossy().into_owned()),
+            has_authenticator: value.has_authenticator(),
+            group: value.group.clone(),
+            tags: value.tags.clone(),
+            favourite: value.favourite,
         }
     }
 }
@@ -24,15 +36,34 @@ impl From<&UserCredentials> for AccountResponse {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountsResponse {
+    /// Favourites first, otherwise in the user's order
     accounts: Vec<AccountResponse>,
     active_account_name: Option<String>,
+    groups: Vec<String>,
+    /// Account files that could not be read, they can be restored from a backup
+    broken_accounts: Vec<BrokenAccount>,
 }
 
-impl From<MutexGuard<'_, AccountsConfig>> for AccountsResponse {
-    fn from(value: MutexGuard<'_, AccountsConfig>) -> Self {
+impl AccountsResponse {
+    pub fn new(
+        accounts_config: &AccountsConfig,
+        group: Option<&str>,
+        avatar_cache: &AvatarCache,
+    ) -> Self {
+        let mut accounts = accounts_config
+            .accounts
+            .iter()
+            .filter(|account| group.is_none_or(|group| account.group.as_deref() == Some(group)))
+            .collect::<Vec<_>>();
+        accounts.sort_by_key(|account| !account.favourite);
         Self {
-            accounts: value.accounts.iter().map(|x| x.into()).collect(),
-            active_account_name: value.active_account_name.clone(),
+            accounts: accounts
+                .into_iter()
+                .map(|account| AccountResponse::new(account, avatar_cache))
+                .collect(),
+            active_account_name: accounts_config.active_account_name.clone(),
+            groups: accounts_config.groups.clone(),
+            broken_accounts: accounts_config.broken_accounts().to_vec(),
         }
     }
 }
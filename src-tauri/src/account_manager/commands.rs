@@ -1,4 +1,14 @@
+use std::sync::MutexGuard;
+
+use validator::Validate;
+
+use super::accounts_config::AccountsConfig;
 use super::accounts_response::{AccountResponse, AccountsResponse};
+use super::payloads::{
+    AccountError, GetAccountsRequest, GroupRequest, RemoveAccountRequest, RenameGroupRequest,
+    ReorderAccountsRequest, SetAccountGroupRequest, SetAccountTagsRequest, SetActiveAccountRequest,
+    SetAliasRequest, SetFavouriteRequest,
+};
 use crate::AppState;
 
 #[tauri::command]
@@ -12,8 +22,16 @@ pub fn is_logged_in(state: tauri::State<'_, AppState>) -> bool {
 }
 
 #[tauri::command]
-pub fn get_accounts(state: tauri::State<'_, AppState>) -> AccountsResponse {
-    state.accounts_config.lock().unwrap().into()
+pub fn get_accounts(
+    state: tauri::State<'_, AppState>,
+    payload: Option<GetAccountsRequest>,
+) -> AccountsResponse {
+    let payload = payload.unwrap_or_default();
+    AccountsResponse::new(
+        &state.accounts_config.lock().unwrap(),
+        payload.group.as_deref(),
+        &state.avatar_cache,
+    )
 }
 
 #[tauri::command]
@@ -23,5 +41,162 @@ pub fn get_active_account(state: tauri::State<'_, AppState>) -> Option<AccountRe
         .lock()
         .unwrap()
         .get_active_account()
-        .map(|x| x.into())
+        .map(|account| AccountResponse::new(account, &state.avatar_cache))
+}
+
+#[tauri::command]
+pub fn set_active_account(
+    state: tauri::State<'_, AppState>,
+    payload: SetActiveAccountRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    if !accounts_config.set_active_account(&payload.account_name) {
+        return Err(AccountError::AccountNotFound);
+    }
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn remove_account(
+    state: tauri::State<'_, AppState>,
+    payload: RemoveAccountRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    let account = accounts_config
+        .accounts
+        .iter()
+        .find(|account| account.account_name == payload.account_name)
+        .ok_or(AccountError::AccountNotFound)?;
+    if account.has_authenticator() && !payload.secrets_backed_up {
+        return Err(AccountError::BackupNotConfirmed);
+    }
+    accounts_config.remove_account(&payload.account_name);
+    save(&accounts_config, &state)?;
+    accounts_config.delete_stale_secrets();
+    log::info!("Removed account {}", payload.account_name);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_account_alias(
+    state: tauri::State<'_, AppState>,
+    mut payload: SetAliasRequest,
+) -> Result<(), AccountError> {
+    payload.alias = payload
+        .alias
+        .map(|alias| alias.trim().to_string())
+        .filter(|alias| !alias.is_empty());
+    payload.validate()?;
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    let account = accounts_config
+        .find_account_mut(&payload.account_name)
+        .ok_or(AccountError::AccountNotFound)?;
+    account.alias = payload.alias;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn create_group(
+    state: tauri::State<'_, AppState>,
+    mut payload: GroupRequest,
+) -> Result<(), AccountError> {
+    payload.name = payload.name.trim().to_string();
+    payload.validate()?;
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    accounts_config.create_group(&payload.name)?;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn rename_group(
+    state: tauri::State<'_, AppState>,
+    mut payload: RenameGroupRequest,
+) -> Result<(), AccountError> {
+    payload.new_name = payload.new_name.trim().to_string();
+    payload.validate()?;
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    accounts_config.rename_group(&payload.name, &payload.new_name)?;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn delete_group(
+    state: tauri::State<'_, AppState>,
+    payload: GroupRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    accounts_config.delete_group(&payload.name)?;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn set_account_group(
+    state: tauri::State<'_, AppState>,
+    payload: SetAccountGroupRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    accounts_config.set_account_group(&payload.account_name, payload.group)?;
+    save(&accounts_config, &state)
+}
+
+/// Blank and repeated tags are dropped
+#[tauri::command]
+pub fn set_account_tags(
+    state: tauri::State<'_, AppState>,
+    mut payload: SetAccountTagsRequest,
+) -> Result<(), AccountError> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in payload.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    payload.tags = tags;
+    payload.validate()?;
+
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    let account = accounts_config
+        .find_account_mut(&payload.account_name)
+        .ok_or(AccountError::AccountNotFound)?;
+    account.tags = payload.tags;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn set_favourite(
+    state: tauri::State<'_, AppState>,
+    payload: SetFavouriteRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    let account = accounts_config
+        .find_account_mut(&payload.account_name)
+        .ok_or(AccountError::AccountNotFound)?;
+    account.favourite = payload.favourite;
+    save(&accounts_config, &state)
+}
+
+#[tauri::command]
+pub fn reorder_accounts(
+    state: tauri::State<'_, AppState>,
+    payload: ReorderAccountsRequest,
+) -> Result<(), AccountError> {
+    let mut accounts_config = unlocked_accounts_config(&state)?;
+    accounts_config.reorder_accounts(&payload.account_names);
+    save(&accounts_config, &state)
+}
+
+fn unlocked_accounts_config<'a>(
+    state: &'a AppState,
+) -> Result<MutexGuard<'a, AccountsConfig>, AccountError> {
+    let accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(AccountError::Locked);
+    }
+    Ok(accounts_config)
+}
+
+fn save(accounts_config: &AccountsConfig, state: &AppState) -> Result<(), AccountError> {
+    accounts_config
+        .save_to_config(&state.config_path)
+        .map_err(|err| AccountError::IOError(err.to_string()))
 }
@@ -0,0 +1,211 @@
+use serde_json::{Map, Value};
+
+use crate::auth::user_credentials::android_device_id;
+
+/// Version written by this build. Bump it together with a new entry in [`MIGRATIONS`]
+/// whenever a stored field changes shape or a new field has no sensible serde default.
+pub const CONFIG_VERSION: u32 = 3;
+
+type JsonObject = Map<String, Value>;
+
+/// `MIGRATIONS[n]` upgrades version `n` to `n + 1`
+const MIGRATIONS: &[Migration] = &[
+    // Version 1 introduced the `version` field itself, the layout did not change
+    Migration {
+        config: |_| {},
+        account: |_| {},
+    },
+    // Version 2 moved every account into its own file next to the manifest. Accounts still
+    // inline in an older config are read as they are and written out on the next save.
+    Migration {
+        config: |_| {},
+        account: |_| {},
+    },
+    // Version 3 replaced the host name stored as `device_id` with the id SDA and steamguard-cli
+    // expect, so exported maFiles work there
+    Migration {
+        config: |_| {},
+        account: replace_host_device_id,
+    },
+];
+
+struct Migration {
+    /// Top level fields of `config.json`
+    config: fn(&mut JsonObject),
+    /// A single account, from an account file, an older inline config or the decrypted vault
+    account: fn(&mut JsonObject),
+}
+
+fn replace_host_device_id(account: &mut JsonObject) {
+    let has_android_id = account
+        .get("device_id")
+        .and_then(Value::as_str)
+        .is_some_and(|device_id| device_id.starts_with("android:"));
+    if has_android_id {
+        return;
+    }
+    // Without a steam id there is nothing to derive the id from
+    let steam_id = account.get("steam_id").and_then(Value::as_u64);
+    if let Some(steam_id) = steam_id.filter(|&steam_id| steam_id != 0) {
+        account.insert("device_id".to_string(), android_device_id(steam_id).into());
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Written by a newer build, which may store fields this one does not know about
+    UnsupportedVersion(u32),
+    InvalidShape,
+}
+
+/// Upgrades a parsed `config.json` in place, returning the version it had before.
+/// Accounts sealed in the vault are upgraded separately with [`migrate_accounts`] on unlock.
+pub fn migrate_config(config: &mut Value) -> Result<u32, MigrationError> {
+    let config = config.as_object_mut().ok_or(MigrationError::InvalidShape)?;
+    let version = match config.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(MigrationError::InvalidShape)?,
+    };
+    for migration in pending_migrations(version)? {
+        (migration.config)(config);
+        if let Some(accounts) = config.get_mut("accounts") {
+            migrate_each_account(accounts, migration)?;
+        }
+    }
+    config.insert("version".to_string(), CONFIG_VERSION.into());
+    Ok(version)
+}
+
+/// Upgrades a list of accounts written with `version`
+pub fn migrate_accounts(accounts: &mut Value, version: u32) -> Result<(), MigrationError> {
+    for migration in pending_migrations(version)? {
+        migrate_each_account(accounts, migration)?;
+    }
+    Ok(())
+}
+
+/// Upgrades a single account written with `version`
+pub fn migrate_account(account: &mut Value, version: u32) -> Result<(), MigrationError> {
+    let account = account
+        .as_object_mut()
+        .ok_or(MigrationError::InvalidShape)?;
+    for migration in pending_migrations(version)? {
+        (migration.account)(account);
+    }
+    Ok(())
+}
+
+fn pending_migrations(version: u32) -> Result<&'static [Migration], MigrationError> {
+    MIGRATIONS
+        .get(version as usize..)
+        .ok_or(MigrationError::UnsupportedVersion(version))
+}
+
+fn migrate_each_account(accounts: &mut Value, migration: &Migration) -> Result<(), MigrationError> {
+    let accounts = accounts
+        .as_array_mut()
+        .ok_or(MigrationError::InvalidShape)?;
+    for account in accounts {
+        let account = account
+            .as_object_mut()
+            .ok_or(MigrationError::InvalidShape)?;
+        (migration.account)(account);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len(), CONFIG_VERSION as usize);
+    }
+
+    #[test]
+    fn stamps_unversioned_config() {
+        let mut config = json!({
+            "accounts": [{ "account_name": "matcha_latte" }],
+            "active_account_name": "matcha_latte",
+        });
+        assert_eq!(migrate_config(&mut config), Ok(0));
+        assert_eq!(config["version"], CONFIG_VERSION);
+        assert_eq!(config["accounts"][0]["account_name"], "matcha_latte");
+    }
+
+    #[test]
+    fn replaces_host_device_id_from_v1_config() {
+        let mut config = json!({
+            "version": 1,
+            "accounts": [
+                {
+                    "account_name": "matcha_latte",
+                    "steam_id": 76561198000000001u64,
+                    "device_id": "matcha-pc (steam-desktop-authenticator-rs)",
+                },
+                {
+                    "account_name": "hojicha",
+                    "steam_id": 76561198000000002u64,
+                    "device_id": "android:5a8cbc1e-7d2b-4e1f-9c3a-0b6d2e4f8a17",
+                },
+                { "account_name": "genmaicha", "steam_id": 0, "device_id": "genmai-pc" },
+            ],
+        });
+        assert_eq!(migrate_config(&mut config), Ok(1));
+        assert_eq!(
+            config["accounts"][0]["device_id"],
+            android_device_id(76561198000000001)
+        );
+        assert_eq!(
+            config["accounts"][1]["device_id"],
+            "android:5a8cbc1e-7d2b-4e1f-9c3a-0b6d2e4f8a17"
+        );
+        assert_eq!(config["accounts"][2]["device_id"], "genmai-pc");
+    }
+
+    #[test]
+    fn migrates_single_account_file() {
+        let mut account = json!({ "steam_id": 76561198000000001u64, "device_id": "matcha-pc" });
+        migrate_account(&mut account, 2).unwrap();
+        assert_eq!(account["device_id"], android_device_id(76561198000000001));
+    }
+
+    #[test]
+    fn leaves_current_config_alone() {
+        let mut config = json!({ "version": CONFIG_VERSION, "accounts": [] });
+        let expected = config.clone();
+        assert_eq!(migrate_config(&mut config), Ok(CONFIG_VERSION));
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn rejects_config_from_newer_build() {
+        let mut config = json!({ "version": CONFIG_VERSION + 1 });
+        assert_eq!(
+            migrate_config(&mut config),
+            Err(MigrationError::UnsupportedVersion(CONFIG_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_config() {
+        assert_eq!(
+            migrate_config(&mut json!({ "version": "one" })),
+            Err(MigrationError::InvalidShape)
+        );
+        assert_eq!(
+            migrate_config(&mut json!({ "accounts": {} })),
+            Err(MigrationError::InvalidShape)
+        );
+        assert_eq!(
+            migrate_config(&mut json!([])),
+            Err(MigrationError::InvalidShape)
+        );
+    }
+}
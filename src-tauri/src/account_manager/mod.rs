@@ -1,3 +1,6 @@
+pub mod account_files;
 pub mod accounts_config;
 pub mod accounts_response;
 pub mod commands;
+pub mod migrations;
+pub mod payloads;
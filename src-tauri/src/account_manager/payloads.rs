@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Longest tag, counted in characters
+const MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetAccountsRequest {
+    /// Only the accounts of this group
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetActiveAccountRequest {
+    pub account_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveAccountRequest {
+    pub account_name: String,
+    /// The user confirmed the secrets are exported or backed up. Without them the
+    /// authenticator can only be removed with the revocation code.
+    pub secrets_backed_up: bool,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAliasRequest {
+    pub account_name: String,
+    /// `None` or blank goes back to showing the account name
+    #[validate(length(max = 64))]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRequest {
+    #[validate(length(min = 1, max = 32))]
+    pub name: String,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameGroupRequest {
+    pub name: String,
+    #[validate(length(min = 1, max = 32))]
+    pub new_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAccountGroupRequest {
+    pub account_name: String,
+    /// `None` takes the account out of its group
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAccountTagsRequest {
+    pub account_name: String,
+    #[validate(length(max = 16), custom(function = validate_tags))]
+    pub tags: Vec<String>,
+}
+
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        return Err(ValidationError::new("tag_too_long")
+            .with_message(format!("Tags can be at most {MAX_TAG_LENGTH} characters long").into()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetFavouriteRequest {
+    pub account_name: String,
+    pub favourite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderAccountsRequest {
+    pub account_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum AccountError {
+    Locked,
+    AccountNotFound,
+    BackupNotConfirmed,
+    GroupNotFound,
+    GroupAlreadyExists,
+    ValidationError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for AccountError {
+    fn from(value: ValidationErrors) -> Self {
+        AccountError::ValidationError(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_overly_long_tags() {
+        let request = SetAccountTagsRequest {
+            account_name: "matcha_latte".to_string(),
+            tags: vec!["trading".to_string(), "t".repeat(MAX_TAG_LENGTH + 1)],
+        };
+        assert!(request.validate().is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use validator::Validate;
+
+use super::payloads::{SettingsError, SettingsResponse, UpdateSettingsRequest};
+use super::settings::AppSettings;
+use crate::AppState;
+
+#[tauri::command]
+pub fn get_settings(state: tauri::State<'_, AppState>) -> SettingsResponse {
+    SettingsResponse::from(&*state.settings.lock().unwrap())
+}
+
+#[tauri::command]
+pub fn update_settings(
+    state: tauri::State<'_, AppState>,
+    payload: UpdateSettingsRequest,
+) -> Result<SettingsResponse, SettingsError> {
+    payload.validate()?;
+    let settings = AppSettings {
+        auto_lock_minutes: payload.auto_lock_minutes,
+        lock_on_suspend: payload.lock_on_suspend,
+        backup_directory: payload.backup_directory,
+        backup_interval_hours: payload.backup_interval_hours,
+        backups_kept: payload.backups_kept,
+    };
+    settings
+        .save(&state.settings_path)
+        .map_err(|err| SettingsError::IOError(err.to_string()))?;
+    let response = SettingsResponse::from(&settings);
+    *state.settings.lock().unwrap() = settings;
+    Ok(response)
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use super::settings::AppSettings;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsResponse {
+    pub auto_lock_minutes: Option<u32>,
+    pub lock_on_suspend: bool,
+    pub backup_directory: Option<String>,
+    pub backup_interval_hours: u32,
+    pub backups_kept: u32,
+}
+
+impl From<&AppSettings> for SettingsResponse {
+    fn from(value: &AppSettings) -> Self {
+        Self {
+            auto_lock_minutes: value.auto_lock_minutes,
+            lock_on_suspend: value.lock_on_suspend,
+            backup_directory: value.backup_directory.clone(),
+            backup_interval_hours: value.backup_interval_hours,
+            backups_kept: value.backups_kept,
+        }
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSettingsRequest {
+    /// `None` turns auto-lock off
+    #[validate(range(min = 1, max = 1440))]
+    pub auto_lock_minutes: Option<u32>,
+    pub lock_on_suspend: bool,
+    /// `None` turns scheduled backups off
+    #[validate(length(min = 1))]
+    pub backup_directory: Option<String>,
+    #[validate(range(min = 1, max = 720))]
+    pub backup_interval_hours: u32,
+    #[validate(range(min = 1, max = 365))]
+    pub backups_kept: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum SettingsError {
+    ValidationError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for SettingsError {
+    fn from(value: ValidationErrors) -> Self {
+        SettingsError::ValidationError(value.to_string())
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+use std::time::Duration;
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::atomic_write::write_atomically;
+
+/// Preferences of this install, kept in `settings.json` next to the config.
+/// Unlike the config, a broken file only costs the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Minutes without a command before a passkey protected vault locks itself, `None` never
+    pub auto_lock_minutes: Option<u32>,
+    /// Lock a passkey protected vault when the computer wakes up from sleep
+    pub lock_on_suspend: bool,
+    /// Where scheduled backup archives go, `None` turns them off. Meant for a synced or
+    /// network folder. They are sealed with the vault's passkey, so they need one set.
+    pub backup_directory: Option<String>,
+    pub backup_interval_hours: u32,
+    /// Older scheduled archives get deleted
+    pub backups_kept: u32,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            auto_lock_minutes: Some(15),
+            lock_on_suspend: true,
+            backup_directory: None,
+            backup_interval_hours: 24,
+            backups_kept: 14,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn load(settings_path: &Path) -> Self {
+        let json_content = match fs::read_to_string(settings_path) {
+            Ok(json_content) => json_content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                log::warn!("Could not read the settings, using the defaults: {err}");
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&json_content).unwrap_or_else(|err| {
+            log::warn!("Could not parse the settings, using the defaults: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, settings_path: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        write_atomically(settings_path, content.as_bytes())
+    }
+
+    pub fn auto_lock_after(&self) -> Option<Duration> {
+        self.auto_lock_minutes
+            .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+    }
+
+    pub fn backup_interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.backup_interval_hours) * 60 * 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_missing_fields_with_defaults() {
+        let settings: AppSettings = serde_json::from_str(r#"{"lock_on_suspend":false}"#).unwrap();
+        assert_eq!(settings.auto_lock_minutes, Some(15));
+        assert!(!settings.lock_on_suspend);
+    }
+
+    #[test]
+    fn never_locks_without_minutes() {
+        let settings = AppSettings {
+            auto_lock_minutes: None,
+            ..Default::default()
+        };
+        assert_eq!(settings.auto_lock_after(), None);
+        assert_eq!(
+            AppSettings::default().auto_lock_after(),
+            Some(Duration::from_secs(15 * 60))
+        );
+    }
+}
@@ -1,20 +1,50 @@
-use std::{fs, sync::Mutex};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use steamguard::{SteamGuardAccount, transport::WebApiTransport};
 use tauri::Manager;
 
-use crate::account_manager::accounts_config::{AccountsConfig, AccountsInitError};
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::app_settings::settings::AppSettings;
+use crate::auth::login_flow::{LoginCancellations, PendingLogin};
+use crate::authenticator::linking::PendingLink;
+use crate::auto_lock::activity::Activity;
+use crate::avatars::cache::AvatarCache;
+use crate::secret_store::cached_store::CachedStore;
+use crate::secret_store::keyring_store::KeyringStore;
+use crate::secret_store::store::SecretStore;
+use crate::steam_time::clock::SteamClock;
+use crate::totp::payloads::CodesFilter;
 
 pub struct AppState {
     pub accounts_config: Mutex<AccountsConfig>,
+    pub config_path: PathBuf,
+    /// Only holds secrets if the user chose to keep them there
+    pub secret_store: Arc<dyn SecretStore>,
+    pub settings: Mutex<AppSettings>,
+    pub settings_path: PathBuf,
+    /// Any command counts, see [`crate::auto_lock`]
+    pub activity: Activity,
     /// Feel free to clone this
     pub transport: WebApiTransport,
+    /// Shared by the transport and every other request, cloning it is cheap
+    pub http_client: reqwest::blocking::Client,
+    pub steam_clock: SteamClock,
+    pub avatar_cache: AvatarCache,
+    pub pending_login: Mutex<Option<PendingLogin>>,
+    /// Cancel through this too, a poll in progress holds the login outside `pending_login`
+    pub login_cancellations: LoginCancellations,
+    pub pending_link: Mutex<Option<PendingLink>>,
+    /// Accounts whose codes get streamed to the frontend, `None` while nobody watches
+    pub codes_filter: Mutex<Option<CodesFilter>>,
 }
 
 impl AppState {
     /// # Panics
     ///
-    /// The function will fail on any initialization error. The state is absolutely necessary for application startup.
+    /// The function will fail if the app directories are unusable. A config that cannot be loaded
+    /// starts the app in recovery mode instead, see [`AccountsConfig::recovering`].
     pub fn init(app: &tauri::App) -> Self {
         let config_dir = app
             .path()
@@ -23,29 +53,52 @@ impl AppState {
         fs::create_dir_all(&config_dir).expect("Could not initialize the configuration path");
         let config_path = config_dir.join("config.json");
 
-        let accounts_config = AccountsConfig::from_config(&config_path)
-            .inspect_err(|err| match err {
-                AccountsInitError::IoError(err) => {
-                    panic!("Could not open the configuration file: {:?}", err)
-                }
-                AccountsInitError::DeserializationError => {
-                    panic!("The account configuration file is corrupted")
-                }
-            })
-            .unwrap();
+        let secret_store: Arc<dyn SecretStore> = Arc::new(CachedStore::new(KeyringStore::new(
+            &app.config().identifier,
+        )));
+        let accounts_config = AccountsConfig::from_config(&config_path, secret_store.clone())
+            .unwrap_or_else(|err| {
+                log::error!("Could not load the config, starting in recovery mode: {err:?}");
+                AccountsConfig::recovering((&err).into())
+            });
         let accounts_config = Mutex::new(accounts_config);
 
-        let transport = WebApiTransport::new(reqwest::blocking::Client::new());
+        let settings_path = config_dir.join("settings.json");
+        let settings = Mutex::new(AppSettings::load(&settings_path));
+
+        let http_client = reqwest::blocking::Client::new();
+        let transport = WebApiTransport::new(http_client.clone());
+        let avatar_cache = match app.path().app_cache_dir() {
+            Ok(cache_dir) => AvatarCache::new(cache_dir.join("avatars")),
+            Err(err) => {
+                log::warn!("Could not get app cache dir, avatars stay disabled: {err}");
+                AvatarCache::disabled()
+            }
+        };
 
         AppState {
             accounts_config,
+            config_path,
+            secret_store,
+            settings,
+            settings_path,
+            activity: Activity::default(),
             transport,
+            http_client,
+            steam_clock: SteamClock::default(),
+            avatar_cache,
+            pending_login: Mutex::new(None),
+            login_cancellations: LoginCancellations::default(),
+            pending_link: Mutex::new(None),
+            codes_filter: Mutex::new(None),
         }
     }
 
     pub fn get_active_steam_guard_account(&self) -> Option<SteamGuardAccount> {
         let accounts_config = self.accounts_config.lock().unwrap();
-        let active_account = accounts_config.get_active_account()?;
+        let active_account = accounts_config
+            .get_active_account()
+            .filter(|account| account.has_authenticator())?;
         let steam_guard_account: SteamGuardAccount = active_account.clone().into();
         Some(steam_guard_account)
     }
@@ -1,94 +1,209 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use super::payloads::{LoginError, LoginRequest};
-use super::user_credentials::UserCredentials;
-use crate::AppState;
-use steamguard::protobufs::steammessages_auth_steamclient::{
-    EAuthSessionGuardType, EAuthTokenPlatformType,
-};
-use steamguard::token::TwoFactorSecret;
-use steamguard::transport::WebApiTransport;
-use steamguard::{DeviceDetails, UserLogin};
+use steamguard::token::Tokens;
 use tauri::{AppHandle, Manager};
 use validator::Validate;
 
+use super::login_flow::{LoginStart, PendingLogin};
+use super::payloads::{
+    LoginCodeRequest, LoginError, LoginRequest, LoginStepResponse, SetAutoReloginRequest,
+};
+use super::user_credentials::{UserCredentials, android_device_id};
+use crate::AppState;
+use crate::common::secret::Secret;
+
+/// Starts a login. Finishes right away when no user input is needed, otherwise the
+/// response says which of the follow-up commands to call.
+#[tauri::command]
+pub async fn login(app: AppHandle, payload: LoginRequest) -> Result<LoginStepResponse, LoginError> {
+    payload.validate()?;
+    with_app_state(app, move |state| {
+        let (shared_secret, identity_secret) = {
+            let accounts_config = state.accounts_config.lock().unwrap();
+            if accounts_config.is_locked() {
+                return Err(LoginError::Locked);
+            }
+            let stored_account = accounts_config
+                .accounts
+                .iter()
+                .find(|account| account.account_name == payload.username)
+                .filter(|account| account.has_authenticator());
+            (
+                payload
+                    .shared_secret
+                    .or_else(|| stored_account.map(|account| account.shared_secret.clone())),
+                payload
+                    .identity_secret
+                    .or_else(|| stored_account.map(|account| account.identity_secret.clone())),
+            )
+        };
+
+        let start = PendingLogin::begin(
+            state.transport.clone(),
+            payload.username,
+            payload.password,
+            shared_secret,
+            identity_secret,
+            state.steam_clock.now(),
+        )?;
+        match start {
+            LoginStart::Finished(pending_login, tokens) => {
+                finish_login(state, pending_login, tokens)
+            }
+            LoginStart::Pending(pending_login, response) => {
+                *state.pending_login.lock().unwrap() = Some(pending_login);
+                Ok(response)
+            }
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn submit_login_email_code(
+    app: AppHandle,
+    payload: LoginCodeRequest,
+) -> Result<LoginStepResponse, LoginError> {
+    payload.validate()?;
+    with_app_state(app, move |state| {
+        let (mut pending_login, generation) = {
+            let mut pending_login = state.pending_login.lock().unwrap();
+            pending_login
+                .as_mut()
+                .ok_or(LoginError::NoPendingLogin)?
+                .submit_email_code(payload.code)?;
+            (
+                pending_login.take().expect("Checked above"),
+                state.login_cancellations.generation(),
+            )
+        };
+        let tokens = pending_login.poll()?;
+        state
+            .login_cancellations
+            .finish_unless_cancelled(generation, || finish_login(state, pending_login, tokens))
+    })
+    .await
+}
+
+/// Waits until the login is approved on a device that has the mobile authenticator
+#[tauri::command]
+pub async fn poll_login(app: AppHandle) -> Result<LoginStepResponse, LoginError> {
+    with_app_state(app, |state| {
+        // Taken out of the state so a long poll does not block cancelling
+        let (mut pending_login, generation) = {
+            let mut pending_login = state.pending_login.lock().unwrap();
+            (
+                pending_login.take().ok_or(LoginError::NoPendingLogin)?,
+                state.login_cancellations.generation(),
+            )
+        };
+        let tokens = pending_login.poll()?;
+        state
+            .login_cancellations
+            .finish_unless_cancelled(generation, || finish_login(state, pending_login, tokens))
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn cancel_login(state: tauri::State<'_, AppState>) {
+    let mut pending_login = state.pending_login.lock().unwrap();
+    // Bumped under the lock, so a poll cannot take the login without seeing the cancel
+    state.login_cancellations.cancel();
+    if let Some(pending_login) = pending_login.take() {
+        log::info!("Cancelled logging in to {}", pending_login.username);
+    }
+}
+
+/// Opts an account in or out of logging in again unattended once its refresh token dies
 #[tauri::command]
-pub fn login(app: AppHandle, payload: LoginRequest) -> Result<(), LoginError> {
+pub fn set_auto_relogin(
+    state: tauri::State<'_, AppState>,
+    payload: SetAutoReloginRequest,
+) -> Result<(), LoginError> {
     payload.validate()?;
-    let config_path = app
-        .path()
-        .app_config_dir()
-        .expect("Expected access to config directory")
-        .join("config.json");
-    let transport = WebApiTransport::new(reqwest::blocking::Client::new());
-    let device_id = format!(
-        "{} (steam-desktop-authenticator-rs)",
-        gethostname::gethostname()
-            .into_string()
-            .expect("failed to get hostname")
-    );
-    // see https://github.com/dyc3/steamguard-cli/blob/4a70af5bfd073604c2afe9f0eb2f0a0d0f4f5113/src/login.rs#L235
-    let mut user_login = UserLogin::new(
-        transport.clone(),
-        DeviceDetails {
-            friendly_name: device_id.clone(),
-            platform_type: EAuthTokenPlatformType::k_EAuthTokenPlatformType_MobileApp,
-            os_type: -500, // Android Unknown
-            gaming_device_type: 528,
-        },
-    );
-    let confirmation_methods = user_login
-        .begin_auth_via_credentials(&payload.username, &payload.password)
-        .or(Err(LoginError::WrongCredentials))?;
-    let is_device_code_available = confirmation_methods.iter().any(|method| {
-        method.confirmation_type == EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode
-    });
-    if !is_device_code_available {
-        return Err(LoginError::Unimplemented);
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(LoginError::Locked);
+    }
+    let account = accounts_config
+        .accounts
+        .iter_mut()
+        .find(|account| account.account_name == payload.account_name)
+        .ok_or(LoginError::AccountNotFound)?;
+    if payload.enabled && (account.account_password.is_empty() || !account.has_authenticator()) {
+        return Err(LoginError::MissingCredentials);
+    }
+    account.auto_relogin = payload.enabled;
+    accounts_config
+        .save_to_config(&state.config_path)
+        .map_err(|err| LoginError::IOError(err.to_string()))
+}
+
+async fn with_app_state<F>(app: AppHandle, f: F) -> Result<LoginStepResponse, LoginError>
+where
+    F: FnOnce(&AppState) -> Result<LoginStepResponse, LoginError> + Send + 'static,
+{
+    let result = tauri::async_runtime::spawn_blocking(move || f(&app.state::<AppState>())).await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            LoginError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+/// Stores the new session. Re-logging an existing account keeps its secrets unless new ones were given.
+fn finish_login(
+    state: &AppState,
+    pending_login: PendingLogin,
+    tokens: Tokens,
+) -> Result<LoginStepResponse, LoginError> {
+    let steam_id = tokens
+        .access_token()
+        .decode()
+        .map_err(|err| LoginError::PollFailed(err.to_string()))?
+        .steam_id();
+    let access_token = Secret::new(tokens.access_token().expose_secret().to_string());
+    let refresh_token = Secret::new(tokens.refresh_token().expose_secret().to_string());
+    let account_name = pending_login.username;
+
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(LoginError::Locked);
     }
-    let two_factor_secret = TwoFactorSecret::parse_shared_secret(payload.shared_secret.clone())
-        .expect("Validation done before");
-    let totp = two_factor_secret.generate_code(
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    );
-    user_login
-        .submit_steam_guard_code(
-            EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode,
-            totp,
-        )
-        .or(Err(LoginError::OtpError))?;
-    let tokens = user_login
-        .poll_until_tokens()
-        .expect("Didn't get any tokens");
-    let user_credentials = UserCredentials {
-        account_name: payload.username,
-        account_password: payload.password,
-        shared_secret: payload.shared_secret,
-        identity_secret: payload.identity_secret,
-        access_token: tokens.access_token().expose_secret().to_string(),
-        refresh_token: tokens.refresh_token().expose_secret().to_string(),
-        device_id: format!(
-            "{} (steam-desktop-authenticator-rs)",
-            gethostname::gethostname()
-                .into_string()
-                .expect("failed to get hostname")
-        ),
-        steam_id: tokens
-            .access_token()
-            .decode()
-            .expect("Could not decode steam auth JWT")
-            .steam_id(),
-        ..Default::default()
-    };
-    let state = app.state::<AppState>();
-    let config = &mut state.accounts_config.lock().unwrap();
-    config.active_account_name = Some(user_credentials.account_name.clone());
-    config.accounts.push(user_credentials);
-    config
-        .save_to_config(&config_path)
+    match accounts_config
+        .accounts
+        .iter_mut()
+        .find(|account| account.account_name == account_name)
+    {
+        Some(account) => {
+            account.steam_id = steam_id;
+            account.account_password = pending_login.password;
+            account.access_token = access_token;
+            account.refresh_token = refresh_token;
+            if let Some(shared_secret) = pending_login.shared_secret {
+                account.shared_secret = shared_secret;
+            }
+            if let Some(identity_secret) = pending_login.identity_secret {
+                account.identity_secret = identity_secret;
+            }
+        }
+        None => accounts_config.accounts.push(UserCredentials {
+            account_name: account_name.clone(),
+            account_password: pending_login.password,
+            shared_secret: pending_login.shared_secret.unwrap_or_default(),
+            identity_secret: pending_login.identity_secret.unwrap_or_default(),
+            access_token,
+            refresh_token,
+            device_id: android_device_id(steam_id),
+            steam_id,
+            ..Default::default()
+        }),
+    }
+    accounts_config.active_account_name = Some(account_name.clone());
+    accounts_config
+        .save_to_config(&state.config_path)
         .map_err(|err| LoginError::IOError(err.to_string()))?;
-    Ok(())
+
+    Ok(LoginStepResponse::Done { account_name })
 }
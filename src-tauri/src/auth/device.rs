@@ -0,0 +1,26 @@
+use steamguard::protobufs::steammessages_auth_steamclient::EAuthTokenPlatformType;
+use steamguard::transport::WebApiTransport;
+use steamguard::{DeviceDetails, UserLogin};
+
+pub fn device_friendly_name() -> String {
+    format!(
+        "{} (steam-desktop-authenticator-rs)",
+        gethostname::gethostname()
+            .into_string()
+            .expect("failed to get hostname")
+    )
+}
+
+/// Logs in as the Steam mobile app, which is required for the authenticator APIs
+pub fn new_user_login(transport: WebApiTransport) -> UserLogin<WebApiTransport> {
+    // see https://github.com/dyc3/steamguard-cli/blob/4a70af5bfd073604c2afe9f0eb2f0a0d0f4f5113/src/login.rs#L235
+    UserLogin::new(
+        transport,
+        DeviceDetails {
+            friendly_name: device_friendly_name(),
+            platform_type: EAuthTokenPlatformType::k_EAuthTokenPlatformType_MobileApp,
+            os_type: -500, // Android Unknown
+            gaming_device_type: 528,
+        },
+    )
+}
@@ -0,0 +1,203 @@
+use std::sync::Mutex;
+
+use steamguard::UserLogin;
+use steamguard::protobufs::steammessages_auth_steamclient::{
+    CAuthentication_AllowedConfirmation, EAuthSessionGuardType,
+};
+use steamguard::token::{Tokens, TwoFactorSecret};
+use steamguard::transport::WebApiTransport;
+
+use super::device::new_user_login;
+use super::payloads::{LoginError, LoginStepResponse};
+use crate::common::secret::Secret;
+
+/// A login waiting for the user to act on a Steam Guard prompt, kept in the app state
+pub struct PendingLogin {
+    pub username: String,
+    pub password: Secret,
+    pub shared_secret: Option<Secret>,
+    pub identity_secret: Option<Secret>,
+    guard_type: EAuthSessionGuardType,
+    user_login: UserLogin<WebApiTransport>,
+}
+
+/// Counts cancelled logins. Polls run with the pending login taken out of the app state, so a
+/// cancel during a poll finds nothing to drop. The poll compares generations instead.
+#[derive(Debug, Default)]
+pub struct LoginCancellations(Mutex<u64>);
+
+impl LoginCancellations {
+    /// Read when taking the pending login out of the app state
+    pub fn generation(&self) -> u64 {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn cancel(&self) {
+        *self.0.lock().unwrap() += 1;
+    }
+
+    /// Runs `finish` unless [`Self::cancel`] was called since `generation`. A cancel waits until
+    /// `finish` returned, so it cannot slip in between the check and storing the session.
+    pub fn finish_unless_cancelled<T>(
+        &self,
+        generation: u64,
+        finish: impl FnOnce() -> Result<T, LoginError>,
+    ) -> Result<T, LoginError> {
+        let current = self.0.lock().unwrap();
+        if *current != generation {
+            return Err(LoginError::Cancelled);
+        }
+        finish()
+    }
+}
+
+/// Result of starting a login: either done right away or waiting on the user
+pub enum LoginStart {
+    Finished(PendingLogin, Tokens),
+    Pending(PendingLogin, LoginStepResponse),
+}
+
+/// Picks the guard Steam offered that needs the least from the user
+fn choose_guard(
+    confirmations: &[CAuthentication_AllowedConfirmation],
+    has_shared_secret: bool,
+) -> Result<&CAuthentication_AllowedConfirmation, LoginError> {
+    let find = |guard_type: EAuthSessionGuardType| {
+        confirmations
+            .iter()
+            .find(|confirmation| confirmation.confirmation_type == guard_type)
+    };
+    let device_code = find(EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode)
+        .filter(|_| has_shared_secret);
+    device_code
+        .or_else(|| find(EAuthSessionGuardType::k_EAuthSessionGuardType_None))
+        .or_else(|| find(EAuthSessionGuardType::k_EAuthSessionGuardType_EmailCode))
+        .or_else(|| find(EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceConfirmation))
+        .ok_or_else(|| {
+            if find(EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode).is_some() {
+                LoginError::SharedSecretRequired
+            } else {
+                LoginError::Unimplemented
+            }
+        })
+}
+
+impl PendingLogin {
+    pub fn begin(
+        transport: WebApiTransport,
+        username: String,
+        password: Secret,
+        shared_secret: Option<Secret>,
+        identity_secret: Option<Secret>,
+        steam_time: u64,
+    ) -> Result<LoginStart, LoginError> {
+        let mut user_login = new_user_login(transport);
+        let confirmations = user_login
+            .begin_auth_via_credentials(&username, password.expose())
+            .or(Err(LoginError::WrongCredentials))?;
+        let guard = choose_guard(&confirmations, shared_secret.is_some())?;
+        let guard_type = guard.confirmation_type;
+        let associated_message = guard.associated_message().to_string();
+
+        let mut pending_login = Self {
+            username,
+            password,
+            shared_secret,
+            identity_secret,
+            guard_type,
+            user_login,
+        };
+        match guard_type {
+            EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode => {
+                let two_factor_secret = TwoFactorSecret::parse_shared_secret(
+                    pending_login
+                        .shared_secret
+                        .clone()
+                        .map(Secret::into_inner)
+                        .unwrap_or_default(),
+                )
+                .or(Err(LoginError::OtpError))?;
+                let totp = two_factor_secret.generate_code(steam_time);
+                pending_login
+                    .user_login
+                    .submit_steam_guard_code(guard_type, totp)
+                    .or(Err(LoginError::OtpError))?;
+                let tokens = pending_login.poll()?;
+                Ok(LoginStart::Finished(pending_login, tokens))
+            }
+            EAuthSessionGuardType::k_EAuthSessionGuardType_None => {
+                let tokens = pending_login.poll()?;
+                Ok(LoginStart::Finished(pending_login, tokens))
+            }
+            EAuthSessionGuardType::k_EAuthSessionGuardType_EmailCode => Ok(LoginStart::Pending(
+                pending_login,
+                LoginStepResponse::AwaitingEmailCode {
+                    email_domain: Some(associated_message).filter(|domain| !domain.is_empty()),
+                },
+            )),
+            _ => Ok(LoginStart::Pending(
+                pending_login,
+                LoginStepResponse::AwaitingDeviceConfirmation,
+            )),
+        }
+    }
+
+    /// Only valid while waiting for an email code. A wrong code keeps the login pending.
+    pub fn submit_email_code(&mut self, code: String) -> Result<(), LoginError> {
+        if self.guard_type != EAuthSessionGuardType::k_EAuthSessionGuardType_EmailCode {
+            return Err(LoginError::UnexpectedStep);
+        }
+        self.user_login
+            .submit_steam_guard_code(self.guard_type, code)
+            .or(Err(LoginError::InvalidCode))?;
+        Ok(())
+    }
+
+    /// Blocks until Steam hands out tokens, e.g. after the login was approved on another device
+    pub fn poll(&mut self) -> Result<Tokens, LoginError> {
+        self.user_login
+            .poll_until_tokens()
+            .map_err(|err| LoginError::PollFailed(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn finishes_an_uncancelled_login() {
+        let cancellations = LoginCancellations::default();
+        let generation = cancellations.generation();
+
+        let result = cancellations.finish_unless_cancelled(generation, || Ok("matcha_latte"));
+
+        assert!(matches!(result, Ok("matcha_latte")));
+    }
+
+    #[test]
+    fn drops_a_login_cancelled_during_the_poll() {
+        let cancellations = LoginCancellations::default();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+        let generation = cancellations.generation();
+
+        let result = thread::scope(|scope| {
+            let cancellations = &cancellations;
+            let poll = scope.spawn(move || {
+                // Steam answers only after the user cancelled
+                cancelled_rx.recv().unwrap();
+                cancellations.finish_unless_cancelled(generation, || -> Result<(), _> {
+                    panic!("Stored the session of a cancelled login")
+                })
+            });
+            cancellations.cancel();
+            cancelled_tx.send(()).unwrap();
+            poll.join().unwrap()
+        });
+
+        assert!(matches!(result, Err(LoginError::Cancelled)));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod commands;
+pub mod device;
+pub mod login_flow;
 pub mod payloads;
+pub mod relogin;
 pub mod user_credentials;
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationErrors};
 
+use crate::common::secret::Secret;
 use crate::common::validators::validate_steam_secret;
 
 #[derive(Debug, Validate, Deserialize)]
@@ -10,13 +11,47 @@ pub struct LoginRequest {
     pub username: String,
 
     #[validate(length(min = 1))]
-    pub password: String,
+    pub password: Secret,
 
+    /// Falls back to the stored secret when re-logging an existing account
     #[validate(length(equal = 28), custom(function = validate_steam_secret))]
-    pub shared_secret: String,
+    pub shared_secret: Option<Secret>,
 
     #[validate(length(equal = 28), custom(function = validate_steam_secret))]
-    pub identity_secret: String,
+    pub identity_secret: Option<Secret>,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginCodeRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+/// What the user has to do next to finish logging in
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "step",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum LoginStepResponse {
+    AwaitingEmailCode {
+        email_domain: Option<String>,
+    },
+    /// Approve the login in the Steam app, then poll
+    AwaitingDeviceConfirmation,
+    Done {
+        account_name: String,
+    },
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAutoReloginRequest {
+    #[validate(length(min = 1))]
+    pub account_name: String,
+    pub enabled: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -26,6 +61,20 @@ pub enum LoginError {
     ValidationError(String),
     OtpError,
     IOError(String),
+    Locked,
+    /// Steam only accepts a mobile authenticator code and no shared secret is known
+    SharedSecretRequired,
+    InvalidCode,
+    NoPendingLogin,
+    /// The login was cancelled or the vault locked while waiting on Steam
+    Cancelled,
+    UnexpectedStep,
+    PollFailed(String),
+    AccountNotFound,
+    /// The account did not opt into logging in again unattended
+    AutoReloginDisabled,
+    /// Unattended logins need the stored password and shared secret
+    MissingCredentials,
     Unimplemented,
 }
 
@@ -43,9 +92,9 @@ mod tests {
     fn fails_login_request_validation_for_non_base64_secrets() {
         let request = LoginRequest {
             username: "matcha_latte".to_string(),
-            password: "Password123!".to_string(),
-            shared_secret: "[];'!!!".to_string(),
-            identity_secret: "~~~~".to_string(),
+            password: "Password123!".into(),
+            shared_secret: Some("[];'!!!".into()),
+            identity_secret: Some("~~~~".into()),
         };
 
         assert!(request.validate().is_err())
@@ -55,9 +104,21 @@ mod tests {
     fn passes_validation_valid_login_request() {
         let request = LoginRequest {
             username: "matcha_latte".to_string(),
-            password: "Password123!".to_string(),
-            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".to_string(),
-            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".to_string(),
+            password: "Password123!".into(),
+            shared_secret: Some("FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into()),
+            identity_secret: Some("FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into()),
+        };
+
+        assert_eq!(request.validate(), Ok(()))
+    }
+
+    #[test]
+    fn passes_validation_without_secrets() {
+        let request = LoginRequest {
+            username: "matcha_latte".to_string(),
+            password: "Password123!".into(),
+            shared_secret: None,
+            identity_secret: None,
         };
 
         assert_eq!(request.validate(), Ok(()))
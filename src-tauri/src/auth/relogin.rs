@@ -0,0 +1,68 @@
+use steamguard::transport::WebApiTransport;
+
+use super::login_flow::{LoginStart, PendingLogin};
+use super::payloads::LoginError;
+use super::user_credentials::UserCredentials;
+use crate::AppState;
+
+/// Replaces both tokens with a fresh credential + TOTP login. Only for accounts that opted in.
+pub fn relogin(
+    transport: WebApiTransport,
+    account: &mut UserCredentials,
+    steam_time: u64,
+) -> Result<(), LoginError> {
+    if !account.auto_relogin {
+        return Err(LoginError::AutoReloginDisabled);
+    }
+    if account.account_password.is_empty() || !account.has_authenticator() {
+        return Err(LoginError::MissingCredentials);
+    }
+
+    let start = PendingLogin::begin(
+        transport,
+        account.account_name.clone(),
+        account.account_password.clone(),
+        Some(account.shared_secret.clone()),
+        Some(account.identity_secret.clone()),
+        steam_time,
+    )?;
+    match start {
+        LoginStart::Finished(_, tokens) => {
+            account.set_tokens(&tokens);
+            Ok(())
+        }
+        // Steam wants an email code or an approval, nobody is around to give it
+        LoginStart::Pending(..) => Err(LoginError::UnexpectedStep),
+    }
+}
+
+/// Re-logs the stored account and saves the new tokens
+pub fn relogin_stored_account(state: &AppState, account_name: &str) -> Result<(), LoginError> {
+    let mut account = {
+        let accounts_config = state.accounts_config.lock().unwrap();
+        if accounts_config.is_locked() {
+            return Err(LoginError::Locked);
+        }
+        accounts_config
+            .accounts
+            .iter()
+            .find(|account| account.account_name == account_name)
+            .cloned()
+            .ok_or(LoginError::AccountNotFound)?
+    };
+    relogin(
+        state.transport.clone(),
+        &mut account,
+        state.steam_clock.now(),
+    )?;
+
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if !accounts_config.store_tokens(&account) {
+        return Err(LoginError::AccountNotFound);
+    }
+    accounts_config
+        .save_to_config(&state.config_path)
+        .map_err(|err| LoginError::IOError(err.to_string()))?;
+    log::info!("Logged in to {account_name} again after its session expired");
+    Ok(())
+}
@@ -1,94 +1,219 @@
-use std::path::Path;
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 use sha1::{Digest as _, Sha1};
-use steamguard::refresher::TokenRefresher;
-use steamguard::steamapi::AuthenticationClient;
+use steamguard::protobufs::steammessages_auth_steamclient::{
+    CAuthentication_AccessToken_GenerateForApp_Request, ETokenRenewalType,
+};
+use steamguard::steamapi::{AuthenticationClient, EResult};
 use steamguard::token::{Jwt, Tokens, TwoFactorSecret};
 use steamguard::transport::Transport;
 use steamguard::{SecretString, SteamGuardAccount};
 
+use crate::common::secret::Secret;
+
+/// Access tokens are refreshed this long before they expire
+pub const REFRESH_MARGIN_SECS: u64 = 5 * 60;
+
+#[derive(Debug)]
+pub enum TokenRefreshError {
+    /// The stored access token is not a valid JWT
+    InvalidToken(String),
+    /// Steam no longer accepts the refresh token. Only a new login helps.
+    RefreshTokenRejected,
+    /// Steam could not be reached or had a temporary failure, worth retrying
+    Network(String),
+}
+
+impl fmt::Display for TokenRefreshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenRefreshError::InvalidToken(err) => write!(f, "invalid access token: {err}"),
+            TokenRefreshError::RefreshTokenRejected => write!(f, "the refresh token was rejected"),
+            TokenRefreshError::Network(err) => write!(f, "could not refresh tokens: {err}"),
+        }
+    }
+}
+
+/// Never panics, a clock set before 1970 reads as `0`
+pub fn unix_now() -> u64 {
+    unix_time(SystemTime::now())
+}
+
+/// Seconds since the epoch, `0` for times before it
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or_default()
+}
+
+/// Secrets are [`Secret`]s, so `Debug` output is safe to log and every copy is wiped on drop
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct UserCredentials {
     pub steam_id: u64,
     pub account_name: String,
-    pub account_password: String,
-    pub shared_secret: String,
-    pub identity_secret: String,
+    pub account_password: Secret,
+    pub shared_secret: Secret,
+    pub identity_secret: Secret,
     /// Set this on first login. Used later to differentiate between sessions
     pub device_id: String,
-    pub revocation_code: Option<String>,
-    pub secret_1: Option<String>,
-    pub access_token: String,
-    pub refresh_token: String,
-    pub cookies: Option<String>,
+    pub revocation_code: Option<Secret>,
+    pub secret_1: Option<Secret>,
+    /// Authenticator serial, only known for authenticators linked or imported here
+    pub serial_number: Option<String>,
+    pub access_token: Secret,
+    pub refresh_token: Secret,
+    pub cookies: Option<Secret>,
+    /// Unix time Steam confirmed the authenticator removal. The secrets are gone after that.
+    pub authenticator_removed_at: Option<u64>,
+    /// Log in again with the stored password and shared secret when Steam rejects the refresh token
+    #[serde(default)]
+    pub auto_relogin: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Display name chosen by the user, the account name is shown without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// One of [`crate::account_manager::accounts_config::AccountsConfig::groups`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favourite: bool,
 }
 
 impl UserCredentials {
-    pub fn retrieve_credentials(path: &Path) -> Vec<UserCredentials> {
-        if !path.exists() {
-            return Vec::new();
+    /// Refreshes the access token if it expires within [`REFRESH_MARGIN_SECS`]
+    pub fn refresh_tokens_if_needed(
+        &mut self,
+        transport: impl Transport,
+    ) -> Result<bool, TokenRefreshError> {
+        let expires_at = self.access_token_expires_at()?;
+        if expires_at > unix_now() + REFRESH_MARGIN_SECS {
+            return Ok(false);
         }
-        unimplemented!()
+        self.refresh_tokens(transport)?;
+        Ok(true)
     }
 
-    pub fn refresh_tokens_if_needed(&mut self, transport: impl Transport) -> Result<bool, String> {
-        let client = AuthenticationClient::new(transport);
-        let mut refresher = TokenRefresher::new(client);
-        let decoded = Jwt::from(self.access_token.clone())
-            .decode()
-            .map_err(|err| err.to_string())?;
-        let is_expired = decoded.exp
-            < SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_secs();
-        if !is_expired {
-            return Ok(false);
+    /// Gets a new access token regardless of expiry, e.g. after Steam refused the current one.
+    /// Steam may also hand out a new refresh token, which replaces the stored one.
+    pub fn refresh_tokens(&mut self, transport: impl Transport) -> Result<(), TokenRefreshError> {
+        let mut client = AuthenticationClient::new(transport);
+        let mut request = CAuthentication_AccessToken_GenerateForApp_Request::new();
+        // The request is consumed by steamguard, so this one copy cannot be wiped from here
+        request.set_refresh_token(self.refresh_token.clone().into_inner());
+        request.set_steamid(self.steam_id);
+        request.set_renewal_type(ETokenRenewalType::k_ETokenRenewalType_Allow);
+        let tokens = self.tokens();
+        let response = client
+            .generate_access_token(request, tokens.access_token())
+            .map_err(|err| TokenRefreshError::Network(err.to_string()))?;
+        match response.result() {
+            EResult::OK => {}
+            EResult::AccessDenied | EResult::Expired | EResult::Revoked | EResult::InvalidParam => {
+                return Err(TokenRefreshError::RefreshTokenRejected);
+            }
+            result => return Err(TokenRefreshError::Network(format!("{result:?}"))),
         }
-        let tokens = Tokens::new(self.access_token.clone(), self.refresh_token.clone());
-        let access_token = refresher
-            .refresh(self.steam_id, &tokens)
-            .map_err(|err| err.to_string())?;
-        self.access_token = access_token.expose_secret().to_owned();
-        Ok(true)
+
+        // Taken out of the response, so it holds no copies once dropped
+        let mut response = response.into_response_data();
+        if response.access_token().is_empty() {
+            return Err(TokenRefreshError::RefreshTokenRejected);
+        }
+        self.access_token = Secret::new(response.take_access_token());
+        if !response.refresh_token().is_empty() {
+            self.refresh_token = Secret::new(response.take_refresh_token());
+        }
+        Ok(())
+    }
+
+    /// Unix time the access token stops working
+    pub fn access_token_expires_at(&self) -> Result<u64, TokenRefreshError> {
+        Jwt::from(self.access_token.expose().to_string())
+            .decode()
+            .map(|decoded| decoded.exp)
+            .map_err(|err| TokenRefreshError::InvalidToken(err.to_string()))
+    }
+
+    pub fn has_authenticator(&self) -> bool {
+        !self.shared_secret.is_empty()
+    }
+
+    /// Copies the tokens into `steamguard`'s own secret types
+    pub fn tokens(&self) -> Tokens {
+        Tokens::new(
+            self.access_token.expose().to_string(),
+            self.refresh_token.expose().to_string(),
+        )
+    }
+
+    /// Moves `steamguard`'s tokens into the account
+    pub fn set_tokens(&mut self, tokens: &Tokens) {
+        self.access_token = Secret::new(tokens.access_token().expose_secret().to_string());
+        self.refresh_token = Secret::new(tokens.refresh_token().expose_secret().to_string());
+    }
+
+    /// Drops everything tied to the removed authenticator. Tokens stay valid.
+    pub fn clear_authenticator(&mut self, removed_at: u64) {
+        self.shared_secret.clear();
+        self.identity_secret.clear();
+        self.revocation_code = None;
+        self.secret_1 = None;
+        self.serial_number = None;
+        self.authenticator_removed_at = Some(removed_at);
     }
 
-    /// Migrated from https://github.com/DoctorMcKay/node-steam-totp/blob/master/index.js#L154
-    ///
     /// To be used for quirky APIs that fail because of the device id
     pub fn consistent_device_id(&self) -> String {
-        let mut hasher = Sha1::new();
-        hasher.update(self.steam_id.to_string().as_bytes());
-        let hash = hex::encode(hasher.finalize());
-        let formatted_id = format!(
-            "{}-{}-{}-{}-{}",
-            &hash[0..8],
-            &hash[8..12],
-            &hash[12..16],
-            &hash[16..20],
-            &hash[20..32]
-        );
-        format!("android:{}", formatted_id)
+        android_device_id(self.steam_id)
     }
 }
 
+/// Migrated from https://github.com/DoctorMcKay/node-steam-totp/blob/master/index.js#L154
+///
+/// The same id the Steam app, SDA and steamguard-cli derive for the account
+pub fn android_device_id(steam_id: u64) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(steam_id.to_string().as_bytes());
+    let hash = hex::encode(hasher.finalize());
+    let formatted_id = format!(
+        "{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    );
+    format!("android:{}", formatted_id)
+}
+
+/// Moves the secrets into `steamguard`'s types, which wipe them on drop. Pass a clone only
+/// when the account has to stay around, the clone's leftovers are wiped as well.
 impl From<UserCredentials> for SteamGuardAccount {
     fn from(value: UserCredentials) -> Self {
         SteamGuardAccount {
             device_id: value.consistent_device_id(),
             account_name: value.account_name,
             steam_id: value.steam_id,
-            serial_number: "".to_string(),
-            revocation_code: value.revocation_code.unwrap_or_default().parse().unwrap(),
-            shared_secret: TwoFactorSecret::parse_shared_secret(value.shared_secret)
+            serial_number: value.serial_number.unwrap_or_default(),
+            revocation_code: value
+                .revocation_code
+                .unwrap_or_default()
+                .expose()
+                .parse()
+                .unwrap(),
+            shared_secret: TwoFactorSecret::parse_shared_secret(value.shared_secret.into_inner())
                 .expect("Validated before"),
-            identity_secret: SecretString::new(value.identity_secret),
+            identity_secret: SecretString::new(value.identity_secret.into_inner()),
             token_gid: "".to_string(),
             uri: SecretString::new("".to_string()),
-            secret_1: SecretString::new("".to_string()),
-            tokens: Some(Tokens::new(value.access_token, value.refresh_token)),
+            secret_1: SecretString::new(value.secret_1.map(Secret::into_inner).unwrap_or_default()),
+            tokens: Some(Tokens::new(
+                value.access_token.into_inner(),
+                value.refresh_token.into_inner(),
+            )),
         }
     }
 }
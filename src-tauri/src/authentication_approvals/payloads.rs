@@ -1,7 +1,6 @@
 use protobuf::Enum;
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
-use steamguard::ApproverError;
 use steamguard::protobufs::enums::ESessionPersistence;
 use steamguard::protobufs::steammessages_auth_steamclient::{
     CAuthentication_GetAuthSessionInfo_Response, EAuthSessionSecurityHistory,
@@ -62,13 +61,6 @@ impl AuthSessionResponse {
     }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum GetApprovalsError {
-    Unauthorized,
-    Unknown,
-}
-
 #[serde_as]
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -86,27 +78,6 @@ pub struct AuthDenyRequest {
     pub client_id: u64,
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum AuthApprovalError {
-    Unknown,
-    Unauthorized,
-    Expired,
-    DuplicateRequest,
-}
-
-impl From<ApproverError> for AuthApprovalError {
-    fn from(err: ApproverError) -> Self {
-        log::debug!("Received authentication request approval error: {err:?}");
-        match err {
-            ApproverError::DuplicateRequest => Self::DuplicateRequest,
-            ApproverError::Expired => Self::Expired,
-            ApproverError::Unauthorized => Self::Unauthorized,
-            _ => Self::Unknown,
-        }
-    }
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApproveQrLoginRequest {
@@ -0,0 +1,238 @@
+use steamguard::transport::WebApiTransport;
+use tauri::{AppHandle, Manager};
+use validator::Validate;
+
+use super::linking::PendingLink;
+use super::payloads::{
+    BeginLinkRequest, LinkCodeRequest, LinkError, LinkPhoneNumberRequest, LinkStepResponse,
+    RemoveAuthenticatorError, RemoveAuthenticatorRequest,
+};
+use super::removal::{remove_authenticator, revoke_then_clear};
+use crate::AppState;
+use crate::auth::user_credentials::{UserCredentials, unix_now};
+
+#[tauri::command]
+pub async fn begin_authenticator_link(
+    app: AppHandle,
+    payload: BeginLinkRequest,
+) -> Result<LinkStepResponse, LinkError> {
+    payload.validate()?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        if state.accounts_config.lock().unwrap().is_locked() {
+            return Err(LinkError::Locked);
+        }
+
+        let (pending_link, response) =
+            PendingLink::begin(state.transport.clone(), payload.username, payload.password)?;
+        save_linked_secrets(&state, &pending_link)?;
+        *state.pending_link.lock().unwrap() = Some(pending_link);
+        Ok(response)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            LinkError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+#[tauri::command]
+pub async fn submit_authenticator_link_email_code(
+    app: AppHandle,
+    payload: LinkCodeRequest,
+) -> Result<LinkStepResponse, LinkError> {
+    payload.validate()?;
+    with_pending_link(app, move |pending_link, transport| {
+        pending_link.submit_email_code(transport, payload.code)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_authenticator_link_phone_number(
+    app: AppHandle,
+    payload: LinkPhoneNumberRequest,
+) -> Result<LinkStepResponse, LinkError> {
+    payload.validate()?;
+    with_pending_link(app, move |pending_link, transport| {
+        pending_link.set_phone_number(transport, &payload.phone_number)
+    })
+    .await
+}
+
+/// Checks again after the user confirmed a link Steam sent by email
+#[tauri::command]
+pub async fn continue_authenticator_link(app: AppHandle) -> Result<LinkStepResponse, LinkError> {
+    with_pending_link(app, |pending_link, transport| {
+        pending_link.resume(transport)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn verify_authenticator_link_phone_code(
+    app: AppHandle,
+    payload: LinkCodeRequest,
+) -> Result<LinkStepResponse, LinkError> {
+    payload.validate()?;
+    with_pending_link(app, move |pending_link, transport| {
+        pending_link.verify_phone_code(transport, payload.code)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn finalize_authenticator_link(
+    app: AppHandle,
+    payload: LinkCodeRequest,
+) -> Result<LinkStepResponse, LinkError> {
+    payload.validate()?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut pending_link = state.pending_link.lock().unwrap();
+        let credentials = pending_link
+            .as_mut()
+            .ok_or(LinkError::NoPendingLink)?
+            .finalize(state.transport.clone(), payload.code)?;
+        let account_name = credentials.account_name.clone();
+        store_linked_account(&state, credentials, true)?;
+        *pending_link = None;
+        log::info!("Linked a new authenticator to {account_name}");
+
+        Ok(LinkStepResponse::Done { account_name })
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            LinkError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+/// Secrets saved before finalization are kept, as Steam may have already activated them
+#[tauri::command]
+pub fn cancel_authenticator_link(state: tauri::State<'_, AppState>) {
+    if let Some(pending_link) = state.pending_link.lock().unwrap().take() {
+        log::info!(
+            "Cancelled linking an authenticator to {}",
+            pending_link.account_name()
+        );
+    }
+}
+
+/// Revokes the authenticator with the stored revocation code. The local secrets
+/// are only cleared once Steam confirmed the removal.
+#[tauri::command]
+pub async fn remove_account_authenticator(
+    app: AppHandle,
+    payload: RemoveAuthenticatorRequest,
+) -> Result<(), RemoveAuthenticatorError> {
+    payload.validate()?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut account = {
+            let accounts_config = state.accounts_config.lock().unwrap();
+            if accounts_config.is_locked() {
+                return Err(RemoveAuthenticatorError::Locked);
+            }
+            accounts_config
+                .accounts
+                .iter()
+                .find(|account| account.account_name == payload.account_name)
+                .cloned()
+                .ok_or(RemoveAuthenticatorError::AccountNotFound)?
+        };
+        account
+            .refresh_tokens_if_needed(state.transport.clone())
+            .map_err(|err| {
+                log::warn!("Could not refresh tokens before removing the authenticator: {err}");
+                RemoveAuthenticatorError::Unauthorized
+            })?;
+
+        revoke_then_clear(
+            &state.accounts_config,
+            &state.config_path,
+            &account,
+            unix_now(),
+            |account| remove_authenticator(state.transport.clone(), account, payload.fallback),
+        )
+        .inspect_err(|err| {
+            log::warn!(
+                "Could not remove the authenticator of {}: {err:?}",
+                payload.account_name
+            )
+        })?;
+        log::info!(
+            "Removed the authenticator of {}, falling back to {:?}",
+            payload.account_name,
+            payload.fallback
+        );
+        Ok(())
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            RemoveAuthenticatorError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+async fn with_pending_link<F>(app: AppHandle, f: F) -> Result<LinkStepResponse, LinkError>
+where
+    F: FnOnce(&mut PendingLink, WebApiTransport) -> Result<LinkStepResponse, LinkError>
+        + Send
+        + 'static,
+{
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut pending_link = state.pending_link.lock().unwrap();
+        let pending_link = pending_link.as_mut().ok_or(LinkError::NoPendingLink)?;
+        let response = f(pending_link, state.transport.clone())?;
+        save_linked_secrets(&state, pending_link)?;
+        Ok(response)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            LinkError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+/// Steam may activate the authenticator even if finalization fails on our side,
+/// so the secrets are written down as soon as they are known
+fn save_linked_secrets(state: &AppState, pending_link: &PendingLink) -> Result<(), LinkError> {
+    match pending_link.linked_credentials() {
+        Some(credentials) => store_linked_account(state, credentials?, false),
+        None => Ok(()),
+    }
+}
+
+fn store_linked_account(
+    state: &AppState,
+    credentials: UserCredentials,
+    make_active: bool,
+) -> Result<(), LinkError> {
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(LinkError::Locked);
+    }
+
+    let account_name = credentials.account_name.clone();
+    accounts_config.import_account(credentials);
+    if make_active || accounts_config.active_account_name.is_none() {
+        accounts_config.active_account_name = Some(account_name);
+    }
+    accounts_config
+        .save_to_config(&state.config_path)
+        .map_err(|err| LinkError::IOError(err.to_string()))
+}
@@ -0,0 +1,352 @@
+use steamguard::accountlinker::AccountLinkConfirmType;
+use steamguard::phonelinker::PhoneLinker;
+use steamguard::protobufs::steammessages_auth_steamclient::EAuthSessionGuardType;
+use steamguard::steamapi::PhoneClient;
+use steamguard::token::Tokens;
+use steamguard::transport::WebApiTransport;
+use steamguard::{
+    AccountLinkError, AccountLinker, FinalizeLinkError, SteamGuardAccount, UserLogin,
+};
+
+use super::payloads::{LinkConfirmType, LinkError, LinkStepResponse};
+use crate::auth::device::new_user_login;
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::secret::Secret;
+use crate::mafiles::steamguard_cli::SteamguardCliAccount;
+
+/// Steam asks to resubmit the activation code with a newer time a few times in a row
+const MAX_FINALIZE_ATTEMPTS: usize = 30;
+
+/// An authenticator link in progress, kept in the app state between commands
+pub struct PendingLink {
+    account_name: String,
+    account_password: Secret,
+    tokens: Option<Tokens>,
+    stage: LinkStage,
+}
+
+enum LinkStage {
+    AwaitingEmailCode(Box<UserLogin<WebApiTransport>>),
+    AwaitingPhoneNumber,
+    AwaitingPhoneEmailConfirmation,
+    AwaitingPhoneCode,
+    AwaitingEmailConfirmation,
+    AwaitingFinalization {
+        account: Box<SteamGuardAccount>,
+        server_time: u64,
+    },
+}
+
+impl PendingLink {
+    pub fn begin(
+        transport: WebApiTransport,
+        username: String,
+        password: Secret,
+    ) -> Result<(Self, LinkStepResponse), LinkError> {
+        let mut user_login = new_user_login(transport.clone());
+        let confirmation_methods = user_login
+            .begin_auth_via_credentials(&username, password.expose())
+            .or(Err(LinkError::WrongCredentials))?;
+        let has_guard = |guard_type: EAuthSessionGuardType| {
+            confirmation_methods
+                .iter()
+                .any(|method| method.confirmation_type == guard_type)
+        };
+        if has_guard(EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceCode)
+            || has_guard(EAuthSessionGuardType::k_EAuthSessionGuardType_DeviceConfirmation)
+        {
+            return Err(LinkError::AuthenticatorPresent);
+        }
+
+        if has_guard(EAuthSessionGuardType::k_EAuthSessionGuardType_EmailCode) {
+            let pending_link = Self {
+                account_name: username,
+                account_password: password,
+                tokens: None,
+                stage: LinkStage::AwaitingEmailCode(Box::new(user_login)),
+            };
+            return Ok((pending_link, LinkStepResponse::AwaitingEmailCode));
+        }
+
+        let tokens = user_login
+            .poll_until_tokens()
+            .map_err(|err| LinkError::ApiError(err.to_string()))?;
+        let mut pending_link = Self {
+            account_name: username,
+            account_password: password,
+            tokens: Some(tokens),
+            stage: LinkStage::AwaitingPhoneNumber,
+        };
+        let response = pending_link.link(transport)?;
+        Ok((pending_link, response))
+    }
+
+    pub fn account_name(&self) -> &str {
+        &self.account_name
+    }
+
+    pub fn submit_email_code(
+        &mut self,
+        transport: WebApiTransport,
+        code: String,
+    ) -> Result<LinkStepResponse, LinkError> {
+        let LinkStage::AwaitingEmailCode(user_login) = &mut self.stage else {
+            return Err(LinkError::UnexpectedStep);
+        };
+        user_login
+            .submit_steam_guard_code(
+                EAuthSessionGuardType::k_EAuthSessionGuardType_EmailCode,
+                code,
+            )
+            .or(Err(LinkError::InvalidCode))?;
+        let tokens = user_login
+            .poll_until_tokens()
+            .map_err(|err| LinkError::ApiError(err.to_string()))?;
+        self.tokens = Some(tokens);
+        self.link(transport)
+    }
+
+    pub fn set_phone_number(
+        &mut self,
+        transport: WebApiTransport,
+        phone_number: &str,
+    ) -> Result<LinkStepResponse, LinkError> {
+        if !matches!(self.stage, LinkStage::AwaitingPhoneNumber) {
+            return Err(LinkError::UnexpectedStep);
+        }
+        let phone_number =
+            phonenumber::parse(None, phone_number).or(Err(LinkError::InvalidPhoneNumber))?;
+        let response = self
+            .phone_linker(transport)?
+            .set_account_phone_number(phone_number)
+            .map_err(|err| LinkError::ApiError(err.to_string()))?;
+        self.stage = LinkStage::AwaitingPhoneEmailConfirmation;
+
+        Ok(LinkStepResponse::AwaitingPhoneEmailConfirmation {
+            email: Some(response.confirmation_email_address().to_string())
+                .filter(|email| !email.is_empty()),
+        })
+    }
+
+    /// Called after the user clicked a confirmation link Steam sent by email
+    pub fn resume(&mut self, transport: WebApiTransport) -> Result<LinkStepResponse, LinkError> {
+        match self.stage {
+            LinkStage::AwaitingPhoneEmailConfirmation => {
+                let phone_linker = self.phone_linker(transport)?;
+                let waiting = phone_linker
+                    .is_account_waiting_for_email_confirmation()
+                    .map_err(|err| LinkError::ApiError(err.to_string()))?;
+                if waiting.awaiting_email_confirmation() {
+                    return Ok(LinkStepResponse::AwaitingPhoneEmailConfirmation { email: None });
+                }
+                phone_linker
+                    .send_phone_verification_code(0)
+                    .map_err(|err| LinkError::ApiError(err.to_string()))?;
+                self.stage = LinkStage::AwaitingPhoneCode;
+                Ok(LinkStepResponse::AwaitingPhoneCode)
+            }
+            LinkStage::AwaitingEmailConfirmation => self.link(transport),
+            _ => Err(LinkError::UnexpectedStep),
+        }
+    }
+
+    pub fn verify_phone_code(
+        &mut self,
+        transport: WebApiTransport,
+        code: String,
+    ) -> Result<LinkStepResponse, LinkError> {
+        if !matches!(self.stage, LinkStage::AwaitingPhoneCode) {
+            return Err(LinkError::UnexpectedStep);
+        }
+        self.phone_linker(transport.clone())?
+            .verify_account_phone_with_code(code)
+            .or(Err(LinkError::InvalidCode))?;
+        self.link(transport)
+    }
+
+    /// The secrets Steam generated for this link. Available before finalization,
+    /// so they can be saved while the user is still waiting for the activation code.
+    pub fn linked_credentials(&self) -> Option<Result<UserCredentials, LinkError>> {
+        match &self.stage {
+            LinkStage::AwaitingFinalization { account, .. } => Some(to_credentials(
+                account,
+                &self.account_password,
+                self.tokens.as_ref(),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn finalize(
+        &mut self,
+        transport: WebApiTransport,
+        code: String,
+    ) -> Result<UserCredentials, LinkError> {
+        let tokens = self.tokens.clone().ok_or(LinkError::UnexpectedStep)?;
+        let LinkStage::AwaitingFinalization {
+            account,
+            server_time,
+        } = &mut self.stage
+        else {
+            return Err(LinkError::UnexpectedStep);
+        };
+
+        let linker = AccountLinker::new(transport, tokens);
+        for _ in 0..MAX_FINALIZE_ATTEMPTS {
+            match linker.finalize(*server_time, account, code.clone()) {
+                Ok(()) => {
+                    return to_credentials(account, &self.account_password, self.tokens.as_ref());
+                }
+                Err(FinalizeLinkError::WantMore {
+                    server_time: next_server_time,
+                }) => *server_time = next_server_time,
+                Err(FinalizeLinkError::BadSmsCode) => return Err(LinkError::InvalidCode),
+                Err(err) => return Err(LinkError::ApiError(err.to_string())),
+            }
+        }
+        Err(LinkError::ApiError(
+            "Steam kept asking for more finalization attempts".to_string(),
+        ))
+    }
+
+    fn link(&mut self, transport: WebApiTransport) -> Result<LinkStepResponse, LinkError> {
+        let tokens = self.tokens.clone().ok_or(LinkError::UnexpectedStep)?;
+        let mut linker = AccountLinker::new(transport, tokens);
+        match linker.link() {
+            Ok(success) => {
+                let confirm_type = match success.confirm_type() {
+                    AccountLinkConfirmType::SMS => LinkConfirmType::Sms,
+                    AccountLinkConfirmType::Email => LinkConfirmType::Email,
+                    _ => LinkConfirmType::Unknown,
+                };
+                let phone_number_hint =
+                    Some(success.phone_number_hint().to_string()).filter(|hint| !hint.is_empty());
+                let server_time = success.server_time();
+                self.stage = LinkStage::AwaitingFinalization {
+                    account: Box::new(success.into_account()),
+                    server_time,
+                };
+                let revocation_code = self
+                    .linked_credentials()
+                    .expect("Just moved to finalization")?
+                    .revocation_code
+                    .map(Secret::into_inner)
+                    .unwrap_or_default();
+
+                Ok(LinkStepResponse::AwaitingFinalizationCode {
+                    confirm_type,
+                    phone_number_hint,
+                    revocation_code,
+                })
+            }
+            Err(AccountLinkError::MustProvidePhoneNumber) => {
+                self.stage = LinkStage::AwaitingPhoneNumber;
+                Ok(LinkStepResponse::AwaitingPhoneNumber)
+            }
+            Err(AccountLinkError::MustConfirmEmail) => {
+                self.stage = LinkStage::AwaitingEmailConfirmation;
+                Ok(LinkStepResponse::AwaitingEmailConfirmation)
+            }
+            Err(AccountLinkError::AuthenticatorPresent) => Err(LinkError::AuthenticatorPresent),
+            Err(AccountLinkError::MustRemovePhoneNumber) => Err(LinkError::MustRemovePhoneNumber),
+            Err(err) => Err(LinkError::ApiError(err.to_string())),
+        }
+    }
+
+    fn phone_linker(
+        &self,
+        transport: WebApiTransport,
+    ) -> Result<PhoneLinker<WebApiTransport>, LinkError> {
+        let tokens = self.tokens.clone().ok_or(LinkError::UnexpectedStep)?;
+        Ok(PhoneLinker::new(PhoneClient::new(transport), tokens))
+    }
+}
+
+/// Goes through the maFile representation, which is the only stable way to get the secrets out
+fn to_credentials(
+    account: &SteamGuardAccount,
+    account_password: &Secret,
+    tokens: Option<&Tokens>,
+) -> Result<UserCredentials, LinkError> {
+    let exported: SteamguardCliAccount = serde_json::to_value(account)
+        .and_then(serde_json::from_value)
+        .map_err(|err| LinkError::ApiError(err.to_string()))?;
+    let mut credentials = exported
+        .into_credentials()
+        .map_err(|err| LinkError::ApiError(format!("Steam returned invalid secrets: {err:?}")))?;
+    credentials.account_password = account_password.clone();
+    if let Some(tokens) = tokens {
+        credentials.set_tokens(tokens);
+    }
+    Ok(credentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport() -> WebApiTransport {
+        WebApiTransport::new(reqwest::blocking::Client::new())
+    }
+
+    fn pending_link(stage: LinkStage) -> PendingLink {
+        PendingLink {
+            account_name: "matcha_latte".to_string(),
+            account_password: "hunter2".into(),
+            tokens: Some(Tokens::new("access".to_string(), "refresh".to_string())),
+            stage,
+        }
+    }
+
+    #[test]
+    fn rejects_steps_out_of_order() {
+        let mut pending_link = pending_link(LinkStage::AwaitingPhoneNumber);
+
+        assert!(matches!(
+            pending_link.submit_email_code(transport(), "ABCDE".to_string()),
+            Err(LinkError::UnexpectedStep)
+        ));
+        assert!(matches!(
+            pending_link.resume(transport()),
+            Err(LinkError::UnexpectedStep)
+        ));
+        assert!(matches!(
+            pending_link.verify_phone_code(transport(), "ABCDE".to_string()),
+            Err(LinkError::UnexpectedStep)
+        ));
+        assert!(matches!(
+            pending_link.finalize(transport(), "ABCDE".to_string()),
+            Err(LinkError::UnexpectedStep)
+        ));
+        assert!(pending_link.linked_credentials().is_none());
+
+        let mut pending_link = pending_link(LinkStage::AwaitingPhoneCode);
+        assert!(matches!(
+            pending_link.set_phone_number(transport(), "+15555550100"),
+            Err(LinkError::UnexpectedStep)
+        ));
+    }
+
+    #[test]
+    fn hands_out_the_secrets_before_finalizing() {
+        let account = SteamGuardAccount::from(UserCredentials {
+            steam_id: 76561198000000000,
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            revocation_code: Some("R12345".into()),
+            ..Default::default()
+        });
+        let pending_link = pending_link(LinkStage::AwaitingFinalization {
+            account: Box::new(account),
+            server_time: 1_700_000_000,
+        });
+
+        let credentials = pending_link.linked_credentials().unwrap().unwrap();
+
+        assert_eq!(credentials.revocation_code, Some("R12345".into()));
+        assert_eq!(credentials.account_password.expose(), "hunter2");
+        assert_eq!(credentials.refresh_token.expose(), "refresh");
+        assert!(credentials.has_authenticator());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod commands;
+pub mod linking;
+pub mod payloads;
+pub mod removal;
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use crate::common::secret::Secret;
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeginLinkRequest {
+    #[validate(length(min = 1))]
+    pub username: String,
+
+    #[validate(length(min = 1))]
+    pub password: Secret,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkCodeRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPhoneNumberRequest {
+    /// In international format, e.g. `+1 123-456-7890`
+    #[validate(length(min = 1))]
+    pub phone_number: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkConfirmType {
+    Sms,
+    Email,
+    Unknown,
+}
+
+/// What the user has to do next to continue linking
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "step",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum LinkStepResponse {
+    AwaitingEmailCode,
+    AwaitingPhoneNumber,
+    /// Steam sent a link to confirm adding the phone number
+    AwaitingPhoneEmailConfirmation {
+        email: Option<String>,
+    },
+    AwaitingPhoneCode,
+    /// Steam requires clicking the link it sent before adding an authenticator
+    AwaitingEmailConfirmation,
+    AwaitingFinalizationCode {
+        confirm_type: LinkConfirmType,
+        phone_number_hint: Option<String>,
+        /// Already saved, but the user should write it down now
+        revocation_code: String,
+    },
+    Done {
+        account_name: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum LinkError {
+    WrongCredentials,
+    AuthenticatorPresent,
+    MustRemovePhoneNumber,
+    NoPendingLink,
+    UnexpectedStep,
+    InvalidCode,
+    InvalidPhoneNumber,
+    Locked,
+    ValidationError(String),
+    ApiError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for LinkError {
+    fn from(value: ValidationErrors) -> Self {
+        LinkError::ValidationError(value.to_string())
+    }
+}
+
+/// What protects the account once the mobile authenticator is gone
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SteamGuardFallback {
+    #[default]
+    EmailCode,
+    None,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveAuthenticatorRequest {
+    #[validate(length(min = 1))]
+    pub account_name: String,
+    #[serde(default)]
+    pub fallback: SteamGuardFallback,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum RemoveAuthenticatorError {
+    AccountNotFound,
+    NoAuthenticator,
+    MissingRevocationCode,
+    /// Carries the number of attempts Steam still allows
+    WrongRevocationCode(u32),
+    Unauthorized,
+    Locked,
+    ValidationError(String),
+    ApiError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for RemoveAuthenticatorError {
+    fn from(value: ValidationErrors) -> Self {
+        RemoveAuthenticatorError::ValidationError(value.to_string())
+    }
+}
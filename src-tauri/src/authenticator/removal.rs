@@ -0,0 +1,200 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use steamguard::protobufs::steammessages_twofactor_steamclient::{
+    CTwoFactor_RemoveAuthenticator_Request, CTwoFactor_RemoveAuthenticator_Response,
+};
+use steamguard::steamapi::{ApiRequest, EResult};
+use steamguard::transport::{Transport, WebApiTransport};
+
+use super::payloads::{RemoveAuthenticatorError, SteamGuardFallback};
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::auth::user_credentials::UserCredentials;
+
+impl SteamGuardFallback {
+    /// Values of `steamguard_scheme` understood by `ITwoFactorService/RemoveAuthenticator`
+    fn steamguard_scheme(self) -> u32 {
+        match self {
+            SteamGuardFallback::EmailCode => 1,
+            SteamGuardFallback::None => 2,
+        }
+    }
+}
+
+/// Asks Steam to revoke the authenticator through `revoke` and clears the stored secrets only
+/// once it confirmed. The session of `account` is stored either way, Steam may have rotated it.
+pub fn revoke_then_clear<F>(
+    accounts_config: &Mutex<AccountsConfig>,
+    config_path: &Path,
+    account: &UserCredentials,
+    removed_at: u64,
+    revoke: F,
+) -> Result<(), RemoveAuthenticatorError>
+where
+    F: FnOnce(&UserCredentials) -> Result<(), RemoveAuthenticatorError>,
+{
+    let revoked = revoke(account);
+
+    let mut accounts_config = accounts_config.lock().unwrap();
+    if !accounts_config.store_tokens(account) {
+        return revoked.and(Err(RemoveAuthenticatorError::AccountNotFound));
+    }
+    if revoked.is_ok() {
+        accounts_config
+            .find_account_mut(&account.account_name)
+            .expect("Found when storing the tokens")
+            .clear_authenticator(removed_at);
+    }
+    accounts_config
+        .save_to_config(config_path)
+        .map_err(|err| RemoveAuthenticatorError::IOError(err.to_string()))?;
+    revoked
+}
+
+/// Asks Steam to revoke the authenticator. Nothing local is touched, so a failure
+/// leaves the account exactly as it was.
+pub fn remove_authenticator(
+    transport: WebApiTransport,
+    account: &UserCredentials,
+    fallback: SteamGuardFallback,
+) -> Result<(), RemoveAuthenticatorError> {
+    if !account.has_authenticator() {
+        return Err(RemoveAuthenticatorError::NoAuthenticator);
+    }
+    let revocation_code = account
+        .revocation_code
+        .clone()
+        .filter(|code| !code.is_empty())
+        .ok_or(RemoveAuthenticatorError::MissingRevocationCode)?;
+    let tokens = account.tokens();
+
+    let mut request = CTwoFactor_RemoveAuthenticator_Request::new();
+    request.set_revocation_code(revocation_code.into_inner());
+    request.set_revocation_reason(1);
+    request.set_steamguard_scheme(fallback.steamguard_scheme());
+    request.set_remove_all_steamguard_cookies(true);
+    let request = ApiRequest::new("ITwoFactorService", "RemoveAuthenticator", 1, request)
+        .with_access_token(tokens.access_token());
+    let response = transport
+        .send_request::<CTwoFactor_RemoveAuthenticator_Request, CTwoFactor_RemoveAuthenticator_Response>(request)
+        .map_err(|err| RemoveAuthenticatorError::ApiError(err.to_string()))?;
+
+    let result = response.result();
+    let response = response.into_response_data();
+    match result {
+        _ if response.success() => Ok(()),
+        // Steam reports a wrong revocation code either way
+        EResult::OK | EResult::TwoFactorCodeMismatch => Err(
+            RemoveAuthenticatorError::WrongRevocationCode(response.revocation_attempts_remaining()),
+        ),
+        EResult::AccessDenied => Err(RemoveAuthenticatorError::Unauthorized),
+        result => Err(RemoveAuthenticatorError::ApiError(format!("{result:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+    use crate::secret_store::memory_store::MemoryStore;
+
+    fn account() -> UserCredentials {
+        UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            revocation_code: Some("R12345".into()),
+            refresh_token: "refresh".into(),
+            ..Default::default()
+        }
+    }
+
+    /// A saved config holding [`account`], plus a copy of it with a rotated refresh token
+    fn saved_config(name: &str) -> (Mutex<AccountsConfig>, PathBuf, UserCredentials) {
+        let config_path = unique_test_dir(name).join("config.json");
+        let mut accounts_config = AccountsConfig::default();
+        accounts_config.accounts.push(account());
+        accounts_config.save_to_config(&config_path).unwrap();
+        let renewed = UserCredentials {
+            refresh_token: "rotated".into(),
+            ..account()
+        };
+        (Mutex::new(accounts_config), config_path, renewed)
+    }
+
+    fn load(config_path: &Path) -> UserCredentials {
+        let accounts_config =
+            AccountsConfig::from_config(config_path, Arc::new(MemoryStore::default())).unwrap();
+        let _ = std::fs::remove_dir_all(config_path.parent().unwrap());
+        accounts_config.accounts[0].clone()
+    }
+
+    #[test]
+    fn maps_fallbacks_to_steam_schemes() {
+        assert_eq!(SteamGuardFallback::EmailCode.steamguard_scheme(), 1);
+        assert_eq!(SteamGuardFallback::None.steamguard_scheme(), 2);
+    }
+
+    #[test]
+    fn keeps_the_secrets_when_steam_refuses() {
+        let (accounts_config, config_path, renewed) = saved_config("removal-refused");
+
+        let result = revoke_then_clear(
+            &accounts_config,
+            &config_path,
+            &renewed,
+            1_700_000_000,
+            |_| Err(RemoveAuthenticatorError::WrongRevocationCode(2)),
+        );
+
+        let stored = load(&config_path);
+        assert!(matches!(
+            result,
+            Err(RemoveAuthenticatorError::WrongRevocationCode(2))
+        ));
+        assert!(stored.has_authenticator());
+        assert_eq!(stored.revocation_code, Some("R12345".into()));
+        assert_eq!(stored.refresh_token.expose(), "rotated");
+        assert_eq!(stored.authenticator_removed_at, None);
+    }
+
+    #[test]
+    fn clears_the_stored_secrets_once_steam_confirms() {
+        let (accounts_config, config_path, renewed) = saved_config("removal-confirmed");
+
+        revoke_then_clear(
+            &accounts_config,
+            &config_path,
+            &renewed,
+            1_700_000_000,
+            |account| {
+                // Steam still gets the revocation code
+                assert_eq!(account.revocation_code, Some("R12345".into()));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let stored = load(&config_path);
+        assert!(!stored.has_authenticator());
+        assert!(stored.identity_secret.is_empty());
+        assert_eq!(stored.revocation_code, None);
+        assert_eq!(stored.refresh_token.expose(), "rotated");
+        assert_eq!(stored.authenticator_removed_at, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn refuses_without_a_revocation_code() {
+        let mut account = account();
+        account.revocation_code = None;
+        let transport = WebApiTransport::new(reqwest::blocking::Client::new());
+
+        assert!(matches!(
+            remove_authenticator(transport, &account, SteamGuardFallback::EmailCode),
+            Err(RemoveAuthenticatorError::MissingRevocationCode)
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::ipc::Invoke;
+use tauri::{Manager, Runtime};
+
+use crate::AppState;
+
+/// When the user last invoked a command
+#[derive(Debug)]
+pub struct Activity {
+    last_seen: Mutex<Instant>,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            last_seen: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Activity {
+    pub fn touch(&self) {
+        *self.last_seen.lock().unwrap() = Instant::now();
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        self.last_seen.lock().unwrap().elapsed()
+    }
+}
+
+/// Invoked on a timer or by a refetch, without the user doing anything
+const PASSIVE_COMMANDS: &[&str] = &[
+    "get_otp",
+    "get_code",
+    "get_codes",
+    "watch_codes",
+    "unwatch_codes",
+    "get_accounts",
+    "get_active_account",
+    "is_logged_in",
+    "get_profile",
+    "get_vault_status",
+    "get_recovery_status",
+    "get_secret_storage",
+    "get_settings",
+    "poll_login",
+];
+
+fn counts_as_activity(command: &str) -> bool {
+    !PASSIVE_COMMANDS.contains(&command)
+}
+
+/// Wraps the command handler, so that the commands the user triggers count as activity
+pub fn track_activity<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if counts_as_activity(invoke.message.command())
+            && let Some(state) = invoke.message.webview().try_state::<AppState>()
+        {
+            state.activity.touch();
+        }
+        handler(invoke)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polling_is_not_activity() {
+        assert!(!counts_as_activity("get_otp"));
+        assert!(!counts_as_activity("get_codes"));
+        assert!(!counts_as_activity("get_accounts"));
+        assert!(!counts_as_activity("get_vault_status"));
+        assert!(counts_as_activity("accept_confirmation"));
+        assert!(counts_as_activity("set_active_account"));
+    }
+}
@@ -0,0 +1,37 @@
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::payloads::{LockReason, VAULT_LOCKED_EVENT, VaultLockedPayload};
+use crate::AppState;
+
+/// Saves, then seals the accounts and drops a half finished login, which holds a password.
+/// Returns `false` if there was nothing to lock.
+pub fn lock_secrets(app: &AppHandle, reason: LockReason) -> bool {
+    let state = app.state::<AppState>();
+    {
+        let mut accounts_config = state.accounts_config.lock().unwrap();
+        if !accounts_config.is_encrypted() || accounts_config.is_locked() {
+            return false;
+        }
+        if let Err(err) = accounts_config.save_to_config(&state.config_path) {
+            // Still locking, the changes stay sealed in memory until the next unlock
+            log::error!("Could not save before locking: {err}");
+        }
+        accounts_config.lock();
+    }
+    {
+        let mut pending_login = state.pending_login.lock().unwrap();
+        state.login_cancellations.cancel();
+        if let Some(pending_login) = pending_login.take() {
+            log::info!(
+                "Cancelled logging in to {} by locking",
+                pending_login.username
+            );
+        }
+    }
+
+    log::info!("Locked the vault ({reason:?})");
+    if let Err(err) = app.emit(VAULT_LOCKED_EVENT, VaultLockedPayload { reason }) {
+        log::error!("Could not emit the lock: {err}");
+    }
+    true
+}
@@ -0,0 +1,5 @@
+pub mod activity;
+pub mod locking;
+pub mod payloads;
+pub mod suspend;
+pub mod watcher;
@@ -0,0 +1,18 @@
+use serde::Serialize;
+
+/// Emitted whenever the vault locks, every secret is gone until it is unlocked again
+pub const VAULT_LOCKED_EVENT: &str = "vault-locked";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockReason {
+    Idle,
+    Suspend,
+    Manual,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultLockedPayload {
+    pub reason: LockReason,
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// The wall clock running ahead of the monotonic one by this much means the computer slept
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Notices a suspend without platform specific signals. The monotonic clock stops while
+/// Linux and macOS sleep, the wall clock does not. On Windows both keep going, there the
+/// idle timeout has to catch it.
+#[derive(Debug)]
+pub struct SuspendDetector {
+    last_check: (Instant, SystemTime),
+}
+
+impl Default for SuspendDetector {
+    fn default() -> Self {
+        Self {
+            last_check: (Instant::now(), SystemTime::now()),
+        }
+    }
+}
+
+impl SuspendDetector {
+    /// Whether the computer slept since the previous call
+    pub fn woke_up(&mut self) -> bool {
+        let (last_instant, last_system_time) = self.last_check;
+        self.last_check = (Instant::now(), SystemTime::now());
+        slept(
+            last_instant.elapsed(),
+            last_system_time.elapsed().unwrap_or_default(),
+        )
+    }
+}
+
+/// A wall clock set forward by hand looks the same, locking then is harmless
+fn slept(monotonic_elapsed: Duration, wall_elapsed: Duration) -> bool {
+    wall_elapsed.saturating_sub(monotonic_elapsed) >= SUSPEND_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_a_gap_between_the_clocks() {
+        assert!(slept(Duration::from_secs(5), Duration::from_secs(60 * 60)));
+        assert!(!slept(Duration::from_secs(5), Duration::from_secs(6)));
+        // The wall clock was set back
+        assert!(!slept(Duration::from_secs(5), Duration::ZERO));
+    }
+}
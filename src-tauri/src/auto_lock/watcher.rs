@@ -0,0 +1,43 @@
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use super::locking::lock_secrets;
+use super::payloads::LockReason;
+use super::suspend::SuspendDetector;
+use crate::AppState;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Locks a passkey protected vault after the configured idle time or a suspend
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("auto-lock".to_string())
+        .spawn(move || {
+            let mut suspend_detector = SuspendDetector::default();
+            loop {
+                thread::sleep(CHECK_INTERVAL);
+                let state = app.state::<AppState>();
+                let woke_up = suspend_detector.woke_up();
+                let settings = state.settings.lock().unwrap().clone();
+
+                let reason = if woke_up && settings.lock_on_suspend {
+                    LockReason::Suspend
+                } else if settings
+                    .auto_lock_after()
+                    .is_some_and(|auto_lock_after| state.activity.idle_for() >= auto_lock_after)
+                {
+                    LockReason::Idle
+                } else {
+                    continue;
+                };
+                // Linking saves the new secrets step by step, locking would make the next step fail
+                if state.pending_link.lock().unwrap().is_some() {
+                    continue;
+                }
+                lock_secrets(&app, reason);
+            }
+        })
+        .expect("Could not spawn the auto-lock thread");
+}
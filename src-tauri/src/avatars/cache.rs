@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Avatars change rarely, a day old picture is good enough
+pub const AVATAR_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Profile pictures of the accounts, one file per Steam ID in the app cache dir
+#[derive(Debug)]
+pub struct AvatarCache {
+    /// `None` when there is no usable cache dir, the accounts are shown without pictures then
+    dir: Option<PathBuf>,
+}
+
+impl AvatarCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir: Some(dir) }
+    }
+
+    /// Keeps no pictures and never asks for new ones
+    pub fn disabled() -> Self {
+        Self { dir: None }
+    }
+
+    /// The cached picture, even an expired one
+    pub fn path(&self, steam_id: u64) -> Option<PathBuf> {
+        self.file_path(steam_id).filter(|path| path.is_file())
+    }
+
+    /// Missing pictures count as expired
+    pub fn is_expired(&self, steam_id: u64) -> bool {
+        let Some(path) = self.file_path(steam_id) else {
+            return false;
+        };
+        let modified = fs::metadata(path).and_then(|meta| meta.modified());
+        match modified {
+            Ok(modified) => is_older_than_ttl(modified, SystemTime::now()),
+            Err(_) => true,
+        }
+    }
+
+    /// Written to a temporary file first, so the frontend never reads a half written picture
+    pub fn store(&self, steam_id: u64, image: &[u8]) -> io::Result<PathBuf> {
+        let (Some(dir), Some(path)) = (&self.dir, self.file_path(steam_id)) else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "The avatar cache is disabled",
+            ));
+        };
+        fs::create_dir_all(dir)?;
+        let temp_path = path.with_extension("jpg.tmp");
+        fs::write(&temp_path, image)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(path)
+    }
+
+    fn file_path(&self, steam_id: u64) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{steam_id}.jpg")))
+    }
+}
+
+fn is_older_than_ttl(modified: SystemTime, now: SystemTime) -> bool {
+    // A modification time in the future means the clock moved, better fetch again
+    match now.duration_since(modified) {
+        Ok(age) => age > AVATAR_TTL,
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_after_ttl() {
+        let now = SystemTime::now();
+        assert!(!is_older_than_ttl(now - Duration::from_secs(60), now));
+        assert!(is_older_than_ttl(now - AVATAR_TTL * 2, now));
+        assert!(is_older_than_ttl(now + Duration::from_secs(60), now));
+    }
+
+    #[test]
+    fn disabled_cache_never_fetches() {
+        let avatar_cache = AvatarCache::disabled();
+        assert!(!avatar_cache.is_expired(76561198000000000));
+        assert_eq!(avatar_cache.path(76561198000000000), None);
+        assert!(avatar_cache.store(76561198000000000, b"jpeg").is_err());
+    }
+}
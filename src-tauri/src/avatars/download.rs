@@ -0,0 +1,69 @@
+use std::io;
+use std::path::PathBuf;
+
+use super::cache::AvatarCache;
+
+/// Steam sends an all zero hash for accounts that never set a picture
+const DEFAULT_AVATAR_HASH: &str = "fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb";
+
+#[derive(Debug)]
+pub enum AvatarError {
+    MissingHash,
+    Network(reqwest::Error),
+    IoError(io::Error),
+}
+
+/// Downloads the picture with the `sha_digest_avatar` hash from `GetPlayerLinkDetails`
+pub fn download_avatar(
+    http_client: &reqwest::blocking::Client,
+    avatar_cache: &AvatarCache,
+    steam_id: u64,
+    sha_digest_avatar: &[u8],
+) -> Result<PathBuf, AvatarError> {
+    let url = avatar_url(sha_digest_avatar).ok_or(AvatarError::MissingHash)?;
+    let image = http_client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map_err(AvatarError::Network)?;
+    avatar_cache
+        .store(steam_id, &image)
+        .map_err(AvatarError::IoError)
+}
+
+fn avatar_url(sha_digest_avatar: &[u8]) -> Option<String> {
+    if sha_digest_avatar.is_empty() {
+        return None;
+    }
+    let hash = if sha_digest_avatar.iter().all(|byte| *byte == 0) {
+        DEFAULT_AVATAR_HASH.to_string()
+    } else {
+        hex::encode(sha_digest_avatar)
+    };
+    Some(format!("https://avatars.steamstatic.com/{hash}_full.jpg"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_url_from_hash() {
+        assert_eq!(
+            avatar_url(&[0xab, 0xcd, 0x01]).as_deref(),
+            Some("https://avatars.steamstatic.com/abcd01_full.jpg")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default_avatar() {
+        assert_eq!(
+            avatar_url(&[0; 20]),
+            Some(format!(
+                "https://avatars.steamstatic.com/{DEFAULT_AVATAR_HASH}_full.jpg"
+            ))
+        );
+        assert_eq!(avatar_url(&[]), None);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cache;
+pub mod download;
+pub mod payloads;
+pub mod sync;
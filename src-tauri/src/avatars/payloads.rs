@@ -0,0 +1,2 @@
+/// Emitted after new pictures land in the cache, the accounts list should be fetched again
+pub const AVATARS_UPDATED_EVENT: &str = "avatars-updated";
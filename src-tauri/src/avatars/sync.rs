@@ -0,0 +1,76 @@
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::download::download_avatar;
+use super::payloads::AVATARS_UPDATED_EVENT;
+use crate::AppState;
+use crate::account::link_details::get_player_link_details;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Downloads missing and expired avatars of all accounts on a background thread
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("avatar-sync".to_string())
+        .spawn(move || {
+            loop {
+                let updated = refresh_expired_avatars(&app.state::<AppState>());
+                if updated > 0 {
+                    log::debug!("Downloaded {updated} avatars");
+                    if let Err(err) = app.emit(AVATARS_UPDATED_EVENT, ()) {
+                        log::error!("Could not emit the avatars update: {err}");
+                    }
+                }
+                thread::sleep(CHECK_INTERVAL);
+            }
+        })
+        .expect("Could not spawn the avatar sync thread");
+}
+
+/// Returns how many pictures were downloaded
+fn refresh_expired_avatars(state: &AppState) -> usize {
+    let (requester, steam_ids) = {
+        let accounts_config = state.accounts_config.lock().unwrap();
+        let steam_ids = accounts_config
+            .accounts
+            .iter()
+            .map(|account| account.steam_id)
+            .filter(|steam_id| *steam_id != 0 && state.avatar_cache.is_expired(*steam_id))
+            .collect::<Vec<_>>();
+        // Any session can read public profiles, the active one is the most likely to be valid
+        let requester = accounts_config
+            .get_active_account()
+            .into_iter()
+            .chain(accounts_config.accounts.iter())
+            .find(|account| !account.access_token.is_empty())
+            .cloned();
+        (requester, steam_ids)
+    };
+    let Some(requester) = requester.filter(|_| !steam_ids.is_empty()) else {
+        return 0;
+    };
+
+    let details = match get_player_link_details(state.transport.clone(), &requester, steam_ids) {
+        Ok(details) => details,
+        Err(err) => {
+            log::warn!("Could not fetch avatar hashes: {err}");
+            return 0;
+        }
+    };
+    let mut downloaded = 0;
+    for details in details {
+        let steam_id = details.public_data.steamid();
+        match download_avatar(
+            &state.http_client,
+            &state.avatar_cache,
+            steam_id,
+            details.public_data.sha_digest_avatar(),
+        ) {
+            Ok(_) => downloaded += 1,
+            Err(err) => log::warn!("Could not download the avatar of {steam_id}: {err:?}"),
+        }
+    }
+    downloaded
+}
@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use super::payloads::ArchiveError;
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::account_manager::migrations::{CONFIG_VERSION, migrate_accounts};
+use crate::app_settings::settings::AppSettings;
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::atomic_write::write_atomically;
+use crate::common::validators::validate_steam_secret;
+use crate::vault::crypto::{KdfParams, VaultKey};
+
+/// Tells archives apart from other JSON files the user might pick
+pub const ARCHIVE_FORMAT: &str = "sda-rs-backup";
+/// Version of the archive layout. The accounts inside carry their own config version.
+pub const ARCHIVE_VERSION: u32 = 1;
+pub const ARCHIVE_EXTENSION: &str = "sdabackup";
+/// Scheduled archives are named `<prefix><timestamp>.<extension>`
+const SCHEDULED_ARCHIVE_PREFIX: &str = "sda-rs-backup-";
+
+/// On-disk shape. Everything but the header is sealed in `payload`.
+#[derive(Serialize, Deserialize)]
+struct ArchiveFile {
+    format: String,
+    version: u32,
+    /// Unix time the archive was written
+    created_at: u64,
+    /// The passkey plus these derive the key, see [`VaultKey`]
+    kdf: KdfParams,
+    payload: String,
+}
+
+/// What an archive holds once opened
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveContent {
+    /// Config version `accounts` were written with, see [`crate::account_manager::migrations`]
+    pub config_version: u32,
+    /// In the user's order
+    pub accounts: Vec<UserCredentials>,
+    pub active_account_name: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub settings: Option<AppSettings>,
+}
+
+#[derive(Debug)]
+pub struct OpenedArchive {
+    pub created_at: u64,
+    pub content: ArchiveContent,
+}
+
+/// Which accounts a restore adds, which it replaces and which it leaves alone
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RestorePlan {
+    pub added: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub kept: Vec<String>,
+}
+
+impl ArchiveContent {
+    /// Everything of an unlocked config, plus the app settings
+    pub fn collect(accounts_config: &AccountsConfig, settings: &AppSettings) -> Self {
+        Self {
+            config_version: CONFIG_VERSION,
+            accounts: accounts_config.accounts.clone(),
+            active_account_name: accounts_config.active_account_name.clone(),
+            groups: accounts_config.groups.clone(),
+            settings: Some(settings.clone()),
+        }
+    }
+
+    /// The checks a restore would otherwise only fail on halfway
+    fn validate(&self) -> Result<(), ArchiveError> {
+        let mut account_names = HashSet::new();
+        for account in &self.accounts {
+            if account.account_name.is_empty() || !account_names.insert(&account.account_name) {
+                return Err(ArchiveError::Corrupted);
+            }
+            if account.has_authenticator()
+                && (validate_steam_secret(&account.shared_secret).is_err()
+                    || validate_steam_secret(&account.identity_secret).is_err())
+            {
+                return Err(ArchiveError::Corrupted);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares the archived accounts with the ones of this install
+    pub fn plan(&self, accounts_config: &AccountsConfig) -> RestorePlan {
+        let archived = |account_name: &str| {
+            self.accounts
+                .iter()
+                .any(|account| account.account_name == account_name)
+        };
+        let (overwritten, added) = self
+            .accounts
+            .iter()
+            .map(|account| account.account_name.clone())
+            .partition(|account_name| accounts_config.find_account(account_name).is_some());
+        let kept = accounts_config
+            .accounts
+            .iter()
+            .map(|account| account.account_name.clone())
+            .filter(|account_name| !archived(account_name))
+            .collect();
+        RestorePlan {
+            added,
+            overwritten,
+            kept,
+        }
+    }
+
+    /// Adds the archived accounts and replaces the ones with the same name. With
+    /// `account_names` only those are restored. Accounts missing from the archive stay.
+    /// The caller is responsible for saving.
+    pub fn merge_into(
+        self,
+        accounts_config: &mut AccountsConfig,
+        account_names: Option<&[String]>,
+    ) -> RestorePlan {
+        let mut restored = RestorePlan::default();
+        for account in self.accounts {
+            let selected = account_names
+                .is_none_or(|account_names| account_names.contains(&account.account_name));
+            if !selected {
+                continue;
+            }
+            if let Some(group) = account.group.as_ref()
+                && !accounts_config.groups.contains(group)
+            {
+                accounts_config.groups.push(group.clone());
+            }
+            let account_name = account.account_name.clone();
+            match accounts_config.find_account_mut(&account_name) {
+                Some(existing) => {
+                    *existing = account;
+                    restored.overwritten.push(account_name);
+                }
+                None => {
+                    accounts_config.accounts.push(account);
+                    restored.added.push(account_name);
+                }
+            }
+        }
+        for group in self.groups {
+            if !accounts_config.groups.contains(&group) {
+                accounts_config.groups.push(group);
+            }
+        }
+        if accounts_config.active_account_name.is_none() {
+            accounts_config.active_account_name = self
+                .active_account_name
+                .filter(|account_name| accounts_config.find_account(account_name).is_some());
+        }
+        restored.kept = accounts_config
+            .accounts
+            .iter()
+            .map(|account| account.account_name.clone())
+            .filter(|account_name| {
+                !restored.added.contains(account_name)
+                    && !restored.overwritten.contains(account_name)
+            })
+            .collect();
+        restored
+    }
+}
+
+/// Seals the content with `vault_key` and writes the archive
+pub fn write_archive(
+    path: &Path,
+    content: &ArchiveContent,
+    vault_key: &VaultKey,
+    created_at: u64,
+) -> io::Result<()> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(content)?);
+    let archive = ArchiveFile {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at,
+        kdf: vault_key.params().clone(),
+        payload: vault_key.seal(&plaintext),
+    };
+    write_atomically(path, serde_json::to_string_pretty(&archive)?.as_bytes())
+}
+
+/// Checks the header, decrypts, migrates and validates the content. Argon2 makes this slow.
+pub fn open_archive(path: &Path, passkey: &str) -> Result<OpenedArchive, ArchiveError> {
+    let json_content = match fs::read_to_string(path) {
+        Ok(json_content) => json_content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(ArchiveError::NotFound),
+        Err(err) => return Err(ArchiveError::IOError(err.to_string())),
+    };
+    let archive: ArchiveFile =
+        serde_json::from_str(&json_content).map_err(|_| ArchiveError::NotAnArchive)?;
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(ArchiveError::NotAnArchive);
+    }
+    if archive.version > ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(archive.version));
+    }
+
+    // A crafted archive must not make opening a preview allocate terabytes
+    if !archive.kdf.is_within_limits() {
+        return Err(ArchiveError::Corrupted);
+    }
+    let vault_key = VaultKey::derive(passkey, archive.kdf)?;
+    let plaintext = vault_key.open(&archive.payload)?;
+    let mut content: Value =
+        serde_json::from_slice(&plaintext).map_err(|_| ArchiveError::Corrupted)?;
+    let config_version = content
+        .get("config_version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or(ArchiveError::Corrupted)?;
+    if config_version > CONFIG_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(config_version));
+    }
+    let accounts = content.get_mut("accounts").ok_or(ArchiveError::Corrupted)?;
+    migrate_accounts(accounts, config_version).map_err(|_| ArchiveError::Corrupted)?;
+    let mut content: ArchiveContent =
+        serde_json::from_value(content).map_err(|_| ArchiveError::Corrupted)?;
+    content.config_version = CONFIG_VERSION;
+    content.validate()?;
+
+    Ok(OpenedArchive {
+        created_at: archive.created_at,
+        content,
+    })
+}
+
+/// Path of a scheduled archive taken at `created_at`. Sorting the names sorts by age.
+pub fn scheduled_archive_path(directory: &Path, created_at: u64) -> PathBuf {
+    directory.join(format!(
+        "{SCHEDULED_ARCHIVE_PREFIX}{created_at:020}.{ARCHIVE_EXTENSION}"
+    ))
+}
+
+/// Scheduled archives in `directory`, oldest first. Archives saved by hand are left out.
+pub fn list_scheduled_archives(directory: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut archives = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == ARCHIVE_EXTENSION)
+                && path.file_stem().is_some_and(|file_stem| {
+                    file_stem
+                        .to_string_lossy()
+                        .starts_with(SCHEDULED_ARCHIVE_PREFIX)
+                })
+        })
+        .collect::<Vec<_>>();
+    archives.sort();
+    archives
+}
+
+/// Unix time the newest scheduled archive in `directory` was taken
+pub fn latest_scheduled_archive(directory: &Path) -> Option<u64> {
+    list_scheduled_archives(directory)
+        .last()?
+        .file_stem()?
+        .to_str()?
+        .strip_prefix(SCHEDULED_ARCHIVE_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Deletes the oldest scheduled archives beyond `keep`
+pub fn prune_scheduled_archives(directory: &Path, keep: usize) -> io::Result<()> {
+    let archives = list_scheduled_archives(directory);
+    let surplus = archives.len().saturating_sub(keep);
+    for archive in &archives[..surplus] {
+        fs::remove_file(archive)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+
+    /// Cheap parameters so the tests don't spend seconds in Argon2
+    fn test_key(passkey: &str) -> VaultKey {
+        let params = KdfParams {
+            memory_cost: 64,
+            time_cost: 1,
+            ..KdfParams::generate()
+        };
+        VaultKey::derive(passkey, params).unwrap()
+    }
+
+    fn account(account_name: &str) -> UserCredentials {
+        UserCredentials {
+            account_name: account_name.to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        }
+    }
+
+    fn accounts_config(account_names: &[&str]) -> AccountsConfig {
+        let mut accounts_config = AccountsConfig::default();
+        accounts_config.accounts = account_names.iter().map(|name| account(name)).collect();
+        accounts_config
+    }
+
+    #[test]
+    fn opens_a_written_archive_with_the_passkey_only() {
+        let directory = unique_test_dir("archive-roundtrip");
+        let path = directory.join("backup.sdabackup");
+        let content = ArchiveContent::collect(
+            &accounts_config(&["matcha_latte", "chai_latte"]),
+            &AppSettings::default(),
+        );
+        write_archive(&path, &content, &test_key("correct horse"), 1_700_000_000).unwrap();
+
+        let raw = fs::read_to_string(&path).unwrap();
+        let wrong = open_archive(&path, "Tr0ub4dor&3");
+        let opened = open_archive(&path, "correct horse");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(!raw.contains("FSY2y2mThnpJv1h+lXKTVuH+cvQ=") && !raw.contains("matcha_latte"));
+        assert!(matches!(wrong, Err(ArchiveError::WrongPasskey)));
+        let opened = opened.unwrap();
+        assert_eq!(opened.created_at, 1_700_000_000);
+        assert_eq!(opened.content.accounts.len(), 2);
+        assert_eq!(opened.content.settings, Some(AppSettings::default()));
+    }
+
+    #[test]
+    fn rejects_other_files_and_newer_archives() {
+        let directory = unique_test_dir("archive-header");
+        let other_path = directory.join("config.json");
+        fs::write(&other_path, r#"{"version":2,"accounts":[]}"#).unwrap();
+        let newer_path = directory.join("newer.sdabackup");
+        let content = ArchiveContent::collect(&accounts_config(&[]), &AppSettings::default());
+        write_archive(&newer_path, &content, &test_key("passkey"), 0).unwrap();
+        let newer = fs::read_to_string(&newer_path)
+            .unwrap()
+            .replace(r#""version": 1"#, r#""version": 99"#);
+        fs::write(&newer_path, newer).unwrap();
+
+        let other = open_archive(&other_path, "passkey");
+        let newer = open_archive(&newer_path, "passkey");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(matches!(other, Err(ArchiveError::NotAnArchive)));
+        assert!(matches!(newer, Err(ArchiveError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    fn rejects_excessive_kdf_params() {
+        let directory = unique_test_dir("archive-kdf-limits");
+        let path = directory.join("crafted.sdabackup");
+        let content = ArchiveContent::collect(&accounts_config(&[]), &AppSettings::default());
+        write_archive(&path, &content, &test_key("passkey"), 0).unwrap();
+        let mut archive: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        archive["kdf"]["memory_cost"] = u32::MAX.into();
+        fs::write(&path, archive.to_string()).unwrap();
+
+        let opened = open_archive(&path, "passkey");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(matches!(opened, Err(ArchiveError::Corrupted)));
+    }
+
+    #[test]
+    fn rejects_duplicate_and_invalid_accounts() {
+        let duplicate = ArchiveContent::collect(
+            &accounts_config(&["matcha_latte", "matcha_latte"]),
+            &AppSettings::default(),
+        );
+        assert!(matches!(duplicate.validate(), Err(ArchiveError::Corrupted)));
+
+        let mut invalid =
+            ArchiveContent::collect(&accounts_config(&["a"]), &AppSettings::default());
+        invalid.accounts[0].shared_secret = "~~~~".into();
+        assert!(matches!(invalid.validate(), Err(ArchiveError::Corrupted)));
+    }
+
+    #[test]
+    fn plans_and_merges_by_account_name() {
+        let content = ArchiveContent {
+            groups: vec!["trading".to_string()],
+            active_account_name: Some("b".to_string()),
+            ..ArchiveContent::collect(&accounts_config(&["a", "b"]), &AppSettings::default())
+        };
+        let mut config = accounts_config(&["b", "c"]);
+        config.accounts[0].alias = Some("Local".to_string());
+
+        assert_eq!(
+            content.plan(&config),
+            RestorePlan {
+                added: vec!["a".to_string()],
+                overwritten: vec!["b".to_string()],
+                kept: vec!["c".to_string()],
+            }
+        );
+        let summary = content.merge_into(&mut config, None);
+
+        assert_eq!(summary.added, ["a"]);
+        assert_eq!(summary.overwritten, ["b"]);
+        assert_eq!(summary.kept, ["c"]);
+        let names = config
+            .accounts
+            .iter()
+            .map(|account| account.account_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["b", "c", "a"]);
+        assert_eq!(config.find_account("b").unwrap().alias, None);
+        assert_eq!(config.groups, ["trading"]);
+        assert_eq!(config.active_account_name.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn merges_only_the_selected_accounts() {
+        let content =
+            ArchiveContent::collect(&accounts_config(&["a", "b"]), &AppSettings::default());
+        let mut config = accounts_config(&[]);
+        let summary = content.merge_into(&mut config, Some(&["b".to_string()]));
+
+        assert_eq!(summary.added, ["b"]);
+        assert_eq!(config.accounts.len(), 1);
+        assert!(summary.kept.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_newest_scheduled_archives() {
+        let directory = unique_test_dir("archive-prune");
+        for created_at in [300, 100, 200] {
+            fs::write(scheduled_archive_path(&directory, created_at), "{}").unwrap();
+        }
+        fs::write(directory.join("by-hand.sdabackup"), "{}").unwrap();
+
+        let latest = latest_scheduled_archive(&directory);
+        prune_scheduled_archives(&directory, 2).unwrap();
+        let remaining = list_scheduled_archives(&directory);
+        let by_hand_kept = directory.join("by-hand.sdabackup").exists();
+        let _ = fs::remove_dir_all(&directory);
+
+        assert_eq!(
+            remaining,
+            [
+                scheduled_archive_path(&directory, 200),
+                scheduled_archive_path(&directory, 300)
+            ]
+        );
+        assert!(by_hand_kept);
+        assert_eq!(latest, Some(300));
+    }
+}
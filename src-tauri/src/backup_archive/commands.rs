@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Manager};
+use validator::Validate;
+
+use super::archive::{ArchiveContent, open_archive, write_archive};
+use super::payloads::{
+    ArchiveError, ArchivePreviewResponse, CreateArchiveRequest, PreviewArchiveRequest,
+    RestoreArchiveRequest, RestoreArchiveResponse,
+};
+use crate::AppState;
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::auth::user_credentials::unix_now;
+use crate::vault::crypto::{KdfParams, VaultKey};
+
+/// Writes every account, the groups and the app settings into one encrypted archive
+#[tauri::command]
+pub async fn create_backup_archive(
+    app: AppHandle,
+    payload: CreateArchiveRequest,
+) -> Result<(), ArchiveError> {
+    payload.validate()?;
+    with_app_state(app, move |state| {
+        let (content, vault_key) = {
+            let accounts_config = state.accounts_config.lock().unwrap();
+            check_usable(&accounts_config)?;
+            let content =
+                ArchiveContent::collect(&accounts_config, &state.settings.lock().unwrap());
+            (content, accounts_config.vault_key().cloned())
+        };
+        let vault_key = match payload.passkey {
+            Some(passkey) => VaultKey::derive(passkey.expose(), KdfParams::generate())?,
+            None => vault_key.ok_or(ArchiveError::PasskeyRequired)?,
+        };
+        write_archive(Path::new(&payload.path), &content, &vault_key, unix_now())
+            .map_err(|err| ArchiveError::IOError(err.to_string()))?;
+        log::info!(
+            "Wrote a backup archive of {} accounts",
+            content.accounts.len()
+        );
+        Ok(())
+    })
+    .await
+}
+
+/// Opens the archive and tells which accounts a restore would add and overwrite
+#[tauri::command]
+pub async fn preview_backup_archive(
+    app: AppHandle,
+    payload: PreviewArchiveRequest,
+) -> Result<ArchivePreviewResponse, ArchiveError> {
+    with_app_state(app, move |state| {
+        let opened = open_archive(Path::new(&payload.path), payload.passkey.expose())?;
+        let accounts_config = state.accounts_config.lock().unwrap();
+        check_usable(&accounts_config)?;
+        let plan = opened.content.plan(&accounts_config);
+        Ok(ArchivePreviewResponse {
+            created_at: opened.created_at,
+            added: plan.added,
+            overwritten: plan.overwritten,
+            kept: plan.kept,
+            includes_settings: opened.content.settings.is_some(),
+        })
+    })
+    .await
+}
+
+/// Merges the archived accounts into this install. Same named accounts are replaced,
+/// the ones missing from the archive stay.
+#[tauri::command]
+pub async fn restore_backup_archive(
+    app: AppHandle,
+    payload: RestoreArchiveRequest,
+) -> Result<RestoreArchiveResponse, ArchiveError> {
+    with_app_state(app, move |state| {
+        let opened = open_archive(Path::new(&payload.path), payload.passkey.expose())?;
+        let settings = opened
+            .content
+            .settings
+            .clone()
+            .filter(|_| payload.restore_settings);
+
+        let restored = {
+            let mut accounts_config = state.accounts_config.lock().unwrap();
+            check_usable(&accounts_config)?;
+            let restored = opened
+                .content
+                .merge_into(&mut accounts_config, payload.account_names.as_deref());
+            accounts_config
+                .save_to_config(&state.config_path)
+                .map_err(|err| ArchiveError::IOError(err.to_string()))?;
+            restored
+        };
+        if let Some(settings) = &settings {
+            settings
+                .save(&state.settings_path)
+                .map_err(|err| ArchiveError::IOError(err.to_string()))?;
+            *state.settings.lock().unwrap() = settings.clone();
+        }
+        log::info!(
+            "Restored {} accounts from a backup archive, {} of them replaced existing ones",
+            restored.added.len() + restored.overwritten.len(),
+            restored.overwritten.len()
+        );
+        Ok(RestoreArchiveResponse::new(restored, settings.is_some()))
+    })
+    .await
+}
+
+fn check_usable(accounts_config: &AccountsConfig) -> Result<(), ArchiveError> {
+    if accounts_config.is_locked() {
+        return Err(ArchiveError::Locked);
+    }
+    if accounts_config.load_error().is_some() {
+        return Err(ArchiveError::Recovering);
+    }
+    Ok(())
+}
+
+/// Argon2 takes a while, so everything runs off the main thread
+async fn with_app_state<T, F>(app: AppHandle, f: F) -> Result<T, ArchiveError>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppState) -> Result<T, ArchiveError> + Send + 'static,
+{
+    let result = tauri::async_runtime::spawn_blocking(move || f(&app.state::<AppState>())).await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            ArchiveError::IOError(err.to_string())
+        })
+        .flatten()
+}
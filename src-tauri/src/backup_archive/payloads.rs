@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use super::archive::RestorePlan;
+use crate::common::secret::Secret;
+use crate::vault::crypto::VaultCryptoError;
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateArchiveRequest {
+    /// Where to write the archive, overwritten if it exists
+    #[validate(length(min = 1))]
+    pub path: String,
+    /// Seals the archive with the vault's passkey when omitted
+    #[validate(length(min = 8))]
+    pub passkey: Option<Secret>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewArchiveRequest {
+    pub path: String,
+    pub passkey: Secret,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreArchiveRequest {
+    pub path: String,
+    pub passkey: Secret,
+    /// Restores only these accounts, all of them when omitted
+    pub account_names: Option<Vec<String>>,
+    /// Replaces the app settings with the archived ones
+    #[serde(default)]
+    pub restore_settings: bool,
+}
+
+/// What a restore would do, shown before the user confirms it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivePreviewResponse {
+    /// Unix time the archive was written
+    pub created_at: u64,
+    /// Not in this install yet
+    pub added: Vec<String>,
+    /// Replaced by the archived version
+    pub overwritten: Vec<String>,
+    /// Only in this install, left as they are
+    pub kept: Vec<String>,
+    pub includes_settings: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreArchiveResponse {
+    pub added: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub kept: Vec<String>,
+    pub settings_restored: bool,
+}
+
+impl RestoreArchiveResponse {
+    pub fn new(restored: RestorePlan, settings_restored: bool) -> Self {
+        Self {
+            added: restored.added,
+            overwritten: restored.overwritten,
+            kept: restored.kept,
+            settings_restored,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum ArchiveError {
+    NotFound,
+    /// Not written by this app
+    NotAnArchive,
+    /// Written by a newer build
+    UnsupportedVersion(u32),
+    /// Either the passkey is wrong or the archive was tampered with
+    WrongPasskey,
+    Corrupted,
+    Locked,
+    /// No passkey was given and the vault has none either
+    PasskeyRequired,
+    /// Accounts can only be restored once the config loads again
+    Recovering,
+    ValidationError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for ArchiveError {
+    fn from(value: ValidationErrors) -> Self {
+        ArchiveError::ValidationError(value.to_string())
+    }
+}
+
+impl From<VaultCryptoError> for ArchiveError {
+    fn from(value: VaultCryptoError) -> Self {
+        match value {
+            VaultCryptoError::DecryptionFailed => ArchiveError::WrongPasskey,
+            VaultCryptoError::InvalidParams | VaultCryptoError::MalformedPayload => {
+                ArchiveError::Corrupted
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use super::archive::{
+    ArchiveContent, latest_scheduled_archive, prune_scheduled_archives, scheduled_archive_path,
+    write_archive,
+};
+use crate::AppState;
+use crate::app_settings::settings::AppSettings;
+use crate::auth::user_credentials::unix_now;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Writes an archive into the configured backup directory whenever the last one is older
+/// than the configured interval
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("backup-archive".to_string())
+        .spawn(move || {
+            // Only warned about once, the setting stays until the user changes it
+            let mut warned_about_passkey = false;
+            loop {
+                let state = app.state::<AppState>();
+                let settings = state.settings.lock().unwrap().clone();
+                if let Some(directory) = &settings.backup_directory {
+                    match write_if_due(&state, Path::new(directory), &settings) {
+                        Ok(()) => {}
+                        Err(ScheduledBackupError::NoPasskey) if warned_about_passkey => {}
+                        Err(ScheduledBackupError::NoPasskey) => {
+                            log::warn!("Scheduled backups need a passkey to seal them with");
+                            warned_about_passkey = true;
+                        }
+                        Err(ScheduledBackupError::IOError(err)) => {
+                            log::error!("Could not write a scheduled backup archive: {err}");
+                        }
+                    }
+                }
+                thread::sleep(CHECK_INTERVAL);
+            }
+        })
+        .expect("Could not spawn the backup archive thread");
+}
+
+enum ScheduledBackupError {
+    NoPasskey,
+    IOError(String),
+}
+
+fn write_if_due(
+    state: &AppState,
+    directory: &Path,
+    settings: &AppSettings,
+) -> Result<(), ScheduledBackupError> {
+    let now = unix_now();
+    let due = latest_scheduled_archive(directory).is_none_or(|created_at| {
+        now.saturating_sub(created_at) >= settings.backup_interval().as_secs()
+    });
+    if !due {
+        return Ok(());
+    }
+
+    let (content, vault_key) = {
+        let accounts_config = state.accounts_config.lock().unwrap();
+        // Picked up on the next check after unlocking
+        if accounts_config.is_locked() || accounts_config.load_error().is_some() {
+            return Ok(());
+        }
+        let vault_key = accounts_config
+            .vault_key()
+            .cloned()
+            .ok_or(ScheduledBackupError::NoPasskey)?;
+        (
+            ArchiveContent::collect(&accounts_config, settings),
+            vault_key,
+        )
+    };
+    fs::create_dir_all(directory)
+        .and_then(|()| {
+            write_archive(
+                &scheduled_archive_path(directory, now),
+                &content,
+                &vault_key,
+                now,
+            )
+        })
+        .and_then(|()| prune_scheduled_archives(directory, settings.backups_kept as usize))
+        .map_err(|err| ScheduledBackupError::IOError(err.to_string()))?;
+    log::info!(
+        "Wrote a scheduled backup archive to {}",
+        directory.display()
+    );
+    Ok(())
+}
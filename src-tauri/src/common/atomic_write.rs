@@ -0,0 +1,56 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use zeroize::Zeroizing;
+
+/// Replaces `path` so that a crash leaves either the old or the new content, never a mix.
+/// The data goes to a temporary file next to it, is flushed to disk and then renamed over.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp_path, path)?;
+
+    // The rename itself only survives a power loss once the directory is flushed too
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// [`write_atomically`] with the content of `from`, unlike [`fs::copy`] flushed to disk
+pub fn copy_atomically(from: &Path, to: &Path) -> io::Result<()> {
+    // Config files may hold plain secrets
+    let contents = Zeroizing::new(fs::read(from)?);
+    write_atomically(to, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+
+    #[test]
+    fn replaces_existing_file_without_leftovers() {
+        let directory = unique_test_dir("atomic-write");
+        let path = directory.join("config.json");
+        fs::write(&path, "old").unwrap();
+
+        write_atomically(&path, b"new").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let files = fs::read_dir(&directory).unwrap().count();
+        let _ = fs::remove_dir_all(&directory);
+        assert_eq!(content, "new");
+        assert_eq!(files, 1);
+    }
+}
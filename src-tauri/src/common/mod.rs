@@ -1 +1,6 @@
+pub mod atomic_write;
+pub mod redaction;
+pub mod secret;
+#[cfg(test)]
+pub mod test_dir;
 pub mod validators;
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::{Zeroize, Zeroizing};
+
+/// A string that is wiped from memory when dropped and never shows up in `Debug` output.
+/// Serialized as the plain string, so stored files keep their shape.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.zeroize();
+    }
+
+    /// Hands the string over without leaving a copy behind, for types of other crates that
+    /// wipe it themselves
+    pub fn into_inner(mut self) -> String {
+        std::mem::take(&mut *self.0)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl AsRef<str> for Secret {
+    fn as_ref(&self) -> &str {
+        self.expose()
+    }
+}
+
+/// Lets request payloads use `#[validate(length(..))]` on secrets
+impl validator::ValidateLength<u64> for Secret {
+    fn length(&self) -> Option<u64> {
+        Some(self.expose().chars().count() as u64)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            f.write_str("Secret(\"\")")
+        } else {
+            f.write_str("Secret(<redacted>)")
+        }
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Overwrites every string in `value`. For JSON that held secrets on its way to a typed struct,
+/// e.g. while migrating an older account.
+pub fn wipe_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(string) => string.zeroize(),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(wipe_json),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(wipe_json),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_value_out_of_debug_output() {
+        let secret = Secret::from("FSY2y2mThnpJv1h+lXKTVuH+cvQ=");
+        assert_eq!(format!("{secret:?}"), "Secret(<redacted>)");
+        assert_eq!(format!("{:?}", Secret::default()), "Secret(\"\")");
+    }
+
+    #[test]
+    fn serializes_as_a_plain_string() {
+        let secret: Secret = serde_json::from_str(r#""FSY2y2mThnpJv1h+lXKTVuH+cvQ=""#).unwrap();
+        assert_eq!(secret.expose(), "FSY2y2mThnpJv1h+lXKTVuH+cvQ=");
+        assert_eq!(
+            serde_json::to_string(&secret).unwrap(),
+            r#""FSY2y2mThnpJv1h+lXKTVuH+cvQ=""#
+        );
+    }
+}
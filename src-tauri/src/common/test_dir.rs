@@ -0,0 +1,11 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// An empty directory for one test. The process id keeps concurrent test runs apart,
+/// `name` the tests within one run.
+pub fn unique_test_dir(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("sda-rs-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
@@ -25,8 +25,8 @@ pub fn validate_base_64_of_length(length: usize, str: &str) -> Result<(), Valida
     }
 }
 
-pub fn validate_steam_secret(str: &str) -> Result<(), ValidationError> {
-    validate_base_64_of_length(20, str)
+pub fn validate_steam_secret<S: AsRef<str> + ?Sized>(secret: &S) -> Result<(), ValidationError> {
+    validate_base_64_of_length(20, secret.as_ref())
 }
 
 #[cfg(test)]
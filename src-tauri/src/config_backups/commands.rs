@@ -0,0 +1,84 @@
+use std::fs;
+
+use super::payloads::{BackupError, ConfigBackupResponse, RestoreBackupRequest};
+use super::rotation::{ConfigBackup, backup_path, list_backups, rotate_backups};
+use crate::AppState;
+use crate::account_manager::account_files::{account_path, list_backed_up_accounts};
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::auth::user_credentials::unix_time;
+use crate::common::atomic_write::write_atomically;
+use crate::config_recovery::broken_config::preserve_broken_config;
+
+/// Backups of the manifest first, then the ones of each account file
+#[tauri::command]
+pub fn list_config_backups(state: tauri::State<'_, AppState>) -> Vec<ConfigBackupResponse> {
+    let config_backups = list_backups(&state.config_path)
+        .into_iter()
+        .map(|backup| backup_response(None, backup));
+    let account_backups = list_backed_up_accounts(&state.config_path)
+        .into_iter()
+        .flat_map(|account_name| {
+            list_backups(&account_path(&state.config_path, &account_name))
+                .into_iter()
+                .map(move |backup| backup_response(Some(account_name.clone()), backup))
+        });
+    config_backups.chain(account_backups).collect()
+}
+
+/// Replaces the manifest, or a single account file, with the backup. The replaced file
+/// becomes the newest backup, so restoring the wrong one can be undone. A restored vault
+/// starts out locked. Restoring the manifest is also the way out of recovery mode.
+#[tauri::command]
+pub fn restore_config_backup(
+    state: tauri::State<'_, AppState>,
+    payload: RestoreBackupRequest,
+) -> Result<(), BackupError> {
+    let restored_path = match &payload.account_name {
+        Some(account_name) => account_path(&state.config_path, account_name),
+        None => state.config_path.clone(),
+    };
+    let json_content = match fs::read_to_string(backup_path(&restored_path, payload.index)) {
+        Ok(json_content) => json_content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(BackupError::NotFound);
+        }
+        Err(err) => return Err(BackupError::IOError(err.to_string())),
+    };
+
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if let Some(account_name) = &payload.account_name {
+        if accounts_config.load_error().is_some() {
+            return Err(BackupError::Recovering);
+        }
+        accounts_config.restore_account(&state.config_path, account_name, &json_content)?;
+        accounts_config
+            .save_to_config(&state.config_path)
+            .map_err(|err| BackupError::IOError(err.to_string()))?;
+        log::info!(
+            "Restored the account {account_name} from backup {}",
+            payload.index
+        );
+        return Ok(());
+    }
+
+    AccountsConfig::validate_manifest(&json_content)?;
+    if accounts_config.load_error().is_some() {
+        preserve_broken_config(&state.config_path)
+            .map_err(|err| BackupError::IOError(err.to_string()))?;
+    }
+    rotate_backups(&state.config_path, true)
+        .and_then(|()| write_atomically(&state.config_path, json_content.as_bytes()))
+        .map_err(|err| BackupError::IOError(err.to_string()))?;
+    *accounts_config = AccountsConfig::from_config(&state.config_path, state.secret_store.clone())?;
+    log::info!("Restored the config from backup {}", payload.index);
+    Ok(())
+}
+
+fn backup_response(account_name: Option<String>, backup: ConfigBackup) -> ConfigBackupResponse {
+    ConfigBackupResponse {
+        account_name,
+        index: backup.index,
+        created_at: unix_time(backup.modified),
+        size: backup.size,
+    }
+}
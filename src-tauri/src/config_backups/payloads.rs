@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::account_manager::accounts_config::AccountsInitError;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigBackupResponse {
+    /// `None` for backups of the manifest
+    pub account_name: Option<String>,
+    /// 1 is the newest
+    pub index: u32,
+    /// Unix time the backup was taken
+    pub created_at: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreBackupRequest {
+    /// Restores one account file instead of the manifest
+    pub account_name: Option<String>,
+    pub index: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum BackupError {
+    NotFound,
+    Corrupted,
+    UnsupportedVersion(u32),
+    /// Single accounts can only be restored once the manifest loads again
+    Recovering,
+    IOError(String),
+}
+
+impl From<AccountsInitError> for BackupError {
+    fn from(value: AccountsInitError) -> Self {
+        match value {
+            AccountsInitError::IoError(err) => BackupError::IOError(err.to_string()),
+            AccountsInitError::DeserializationError(_) | AccountsInitError::InvalidShape => {
+                BackupError::Corrupted
+            }
+            AccountsInitError::UnsupportedVersion(version) => {
+                BackupError::UnsupportedVersion(version)
+            }
+            AccountsInitError::SecretStore(err) => BackupError::IOError(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::common::atomic_write::copy_atomically;
+
+/// How many old versions of a config or account file are kept next to it
+pub const BACKUP_COUNT: u32 = 5;
+/// Token refreshes save every few minutes. Without this the backups would only differ in tokens.
+const MIN_BACKUP_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug)]
+pub struct ConfigBackup {
+    /// 1 is the newest
+    pub index: u32,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub size: u64,
+}
+
+/// `config.json` becomes `config.{index}.json.bak`
+pub fn backup_path(config_path: &Path, index: u32) -> PathBuf {
+    config_path.with_extension(format!("{index}.json.bak"))
+}
+
+/// `config.json` becomes `config.v{version}.json.bak`, copied before migrating it
+pub fn migration_backup_path(config_path: &Path, version: u32) -> PathBuf {
+    config_path.with_extension(format!("v{version}.json.bak"))
+}
+
+/// Shifts the backups by one and copies the current config into the newest slot.
+/// Unless `force` is set, nothing happens while the newest backup is recent.
+pub fn rotate_backups(config_path: &Path, force: bool) -> io::Result<()> {
+    if !config_path.exists() {
+        return Ok(());
+    }
+    if !force && is_recent(&backup_path(config_path, 1)) {
+        return Ok(());
+    }
+
+    match fs::remove_file(backup_path(config_path, BACKUP_COUNT)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    for index in (1..BACKUP_COUNT).rev() {
+        let path = backup_path(config_path, index);
+        if path.exists() {
+            fs::rename(&path, backup_path(config_path, index + 1))?;
+        }
+    }
+    copy_atomically(config_path, &backup_path(config_path, 1))?;
+    Ok(())
+}
+
+/// For when the backups hold secrets that are not supposed to be on disk anymore
+pub fn remove_backups(path: &Path) -> io::Result<()> {
+    for index in 1..=BACKUP_COUNT {
+        match fs::remove_file(backup_path(path, index)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Removes every [`migration_backup_path`] of the config, whichever version it was from
+pub fn remove_migration_backups(config_path: &Path) -> io::Result<()> {
+    let (Some(directory), Some(file_stem)) = (
+        config_path.parent(),
+        config_path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str()),
+    ) else {
+        return Ok(());
+    };
+    let entries = match fs::read_dir(directory) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        entries => entries?,
+    };
+    for entry in entries {
+        let entry = entry?;
+        let is_migration_backup = entry.file_name().to_str().is_some_and(|file_name| {
+            file_name
+                .strip_prefix(file_stem)
+                .and_then(|rest| rest.strip_prefix(".v"))
+                .and_then(|rest| rest.strip_suffix(".json.bak"))
+                .is_some_and(|version| version.parse::<u32>().is_ok())
+        });
+        if is_migration_backup {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Newest first
+pub fn list_backups(config_path: &Path) -> Vec<ConfigBackup> {
+    (1..=BACKUP_COUNT)
+        .filter_map(|index| {
+            let path = backup_path(config_path, index);
+            let metadata = fs::metadata(&path).ok()?;
+            Some(ConfigBackup {
+                index,
+                modified: metadata.modified().ok()?,
+                size: metadata.len(),
+                path,
+            })
+        })
+        .collect()
+}
+
+fn is_recent(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < MIN_BACKUP_AGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let directory = unique_test_dir("config-rotation");
+        let config_path = directory.join("config.json");
+
+        for generation in 0..BACKUP_COUNT + 2 {
+            fs::write(&config_path, generation.to_string()).unwrap();
+            rotate_backups(&config_path, true).unwrap();
+        }
+        // Recent backups are not rotated without force
+        fs::write(&config_path, "skipped").unwrap();
+        rotate_backups(&config_path, false).unwrap();
+
+        let backups = list_backups(&config_path);
+        let newest = fs::read_to_string(&backups[0].path).unwrap();
+        let oldest = fs::read_to_string(&backups.last().unwrap().path).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+        assert_eq!(backups.len(), BACKUP_COUNT as usize);
+        assert_eq!(newest, (BACKUP_COUNT + 1).to_string());
+        assert_eq!(oldest, "2");
+    }
+}
@@ -0,0 +1,15 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::auth::user_credentials::unix_now;
+
+/// Copies the unreadable config aside before it gets replaced. Unlike the rotated backups,
+/// these copies are never deleted by the app.
+pub fn preserve_broken_config(config_path: &Path) -> io::Result<PathBuf> {
+    let now = unix_now();
+    let preserved_path = config_path.with_extension(format!("broken-{now}.json"));
+    fs::copy(config_path, &preserved_path)?;
+    log::info!("Kept the unreadable config as {}", preserved_path.display());
+    Ok(preserved_path)
+}
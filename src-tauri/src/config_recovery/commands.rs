@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::Path;
+
+use super::broken_config::preserve_broken_config;
+use super::payloads::{RecoveryError, RecoveryStatusResponse};
+use crate::AppState;
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::config_backups::commands::list_config_backups;
+
+/// `None` unless the app started in recovery mode
+#[tauri::command]
+pub fn get_recovery_status(state: tauri::State<'_, AppState>) -> Option<RecoveryStatusResponse> {
+    let error = state.accounts_config.lock().unwrap().load_error()?.clone();
+    let salvaged = read_broken_config(&state.config_path)
+        .map(|json_content| {
+            AccountsConfig::salvage(
+                &state.config_path,
+                &json_content,
+                state.secret_store.clone(),
+            )
+        })
+        .unwrap_or_default();
+    Some(RecoveryStatusResponse {
+        error,
+        salvageable_accounts: salvaged.stored_account_names(),
+        salvageable_vault: salvaged.is_locked(),
+        backups: list_config_backups(state.clone()),
+    })
+}
+
+/// Replaces the broken manifest with whatever could be parsed out of it and the account files
+#[tauri::command]
+pub fn salvage_config(state: tauri::State<'_, AppState>) -> Result<(), RecoveryError> {
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.load_error().is_none() {
+        return Err(RecoveryError::NotRecovering);
+    }
+    let json_content = read_broken_config(&state.config_path)
+        .map_err(|err| RecoveryError::IOError(err.to_string()))?;
+    let salvaged = AccountsConfig::salvage(
+        &state.config_path,
+        &json_content,
+        state.secret_store.clone(),
+    );
+    if salvaged.stored_account_names().is_empty() && !salvaged.is_locked() {
+        return Err(RecoveryError::NothingToSalvage);
+    }
+
+    preserve_broken_config(&state.config_path)
+        .and_then(|_| salvaged.save_to_config(&state.config_path))
+        .map_err(|err| RecoveryError::IOError(err.to_string()))?;
+    log::info!(
+        "Salvaged {} accounts from the broken config",
+        salvaged.stored_account_names().len()
+    );
+    *accounts_config = salvaged;
+    Ok(())
+}
+
+/// A single bad byte may not even be valid UTF-8
+fn read_broken_config(config_path: &Path) -> std::io::Result<String> {
+    fs::read(config_path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+}
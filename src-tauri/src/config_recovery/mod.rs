@@ -0,0 +1,4 @@
+pub mod broken_config;
+pub mod commands;
+pub mod payloads;
+pub mod salvage;
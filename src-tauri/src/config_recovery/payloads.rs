@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+use crate::account_manager::accounts_config::AccountsInitError;
+use crate::config_backups::payloads::ConfigBackupResponse;
+
+/// Why the config could not be loaded. The position is 1-based, as in serde_json.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigLoadError {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl From<&AccountsInitError> for ConfigLoadError {
+    fn from(value: &AccountsInitError) -> Self {
+        let (message, position) = match value {
+            AccountsInitError::IoError(err) => (err.to_string(), None),
+            AccountsInitError::DeserializationError(err) => (
+                err.to_string(),
+                Some((err.line(), err.column())).filter(|(line, _)| *line > 0),
+            ),
+            AccountsInitError::InvalidShape => (
+                "The config does not have the expected layout".to_string(),
+                None,
+            ),
+            AccountsInitError::UnsupportedVersion(version) => (
+                format!("The config has version {version}, it was written by a newer app"),
+                None,
+            ),
+            AccountsInitError::SecretStore(err) => (err.to_string(), None),
+        };
+        Self {
+            message,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryStatusResponse {
+    pub error: ConfigLoadError,
+    /// Accounts a salvage would keep
+    pub salvageable_accounts: Vec<String>,
+    /// A salvage would keep the passkey protected vault, still locked
+    pub salvageable_vault: bool,
+    pub backups: Vec<ConfigBackupResponse>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum RecoveryError {
+    NotRecovering,
+    NothingToSalvage,
+    IOError(String),
+}
@@ -0,0 +1,54 @@
+use serde::de::DeserializeOwned;
+
+/// Every JSON object in `text` that deserializes as `T`, even if the text as a whole is broken.
+/// Objects nested inside a match are not looked at separately.
+pub fn scan_objects<T: DeserializeOwned>(text: &str) -> Vec<T> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    while let Some(offset) = text[start..].find('{') {
+        let position = start + offset;
+        let mut stream = serde_json::Deserializer::from_str(&text[position..]).into_iter::<T>();
+        match stream.next() {
+            Some(Ok(object)) => {
+                found.push(object);
+                start = position + stream.byte_offset();
+            }
+            _ => start = position + 1,
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Account {
+        account_name: String,
+        shared_secret: String,
+    }
+
+    #[test]
+    fn finds_accounts_around_a_broken_one() {
+        let text = r#"{"accounts": [
+            {"account_name": "matcha_latte", "shared_secret": "a"},
+            {"account_name": "espr\u0000 broken,
+            {"account_name": "cappuccino", "shared_secret": "c"}
+        ], "active_account_name": nul"#;
+        let accounts = scan_objects::<Account>(text);
+        let account_names = accounts
+            .iter()
+            .map(|account| account.account_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(account_names, ["matcha_latte", "cappuccino"]);
+    }
+
+    #[test]
+    fn skips_objects_of_another_shape() {
+        let text = r#"{"kdf": {"salt": "x"}, "accounts": "sealed"}"#;
+        assert!(scan_objects::<Account>(text).is_empty());
+    }
+}
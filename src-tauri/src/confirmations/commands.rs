@@ -1,182 +1,84 @@
-use steamguard::{Confirmer, ConfirmerError};
+use steamguard::Confirmer;
+use tauri::AppHandle;
 
-use super::payloads::{ConfirmationResponse, GetConfirmationsError};
-use crate::app_state::AppState;
-use crate::confirmations::payloads::{
-    ConfirmationActionRequest, ConfirmationDetailsResponse, ConfirmationError,
-};
+use super::payloads::ConfirmationResponse;
+use crate::confirmations::payloads::{ConfirmationActionRequest, ConfirmationDetailsResponse};
+use crate::session::executor::with_active_account;
+use crate::session::payloads::SteamError;
 
 // TODO: Ideally make the API calls async instead of running them in another thread
 
 #[tauri::command]
-pub async fn get_confirmations(
-    state: tauri::State<'_, AppState>,
-) -> Result<Vec<ConfirmationResponse>, GetConfirmationsError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(GetConfirmationsError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-
-        let confirmations = confirmer.get_confirmations().map_err(|err| {
-            log::debug!("Encountered error when fetching confirmations from Steam: {err:?}");
-            GetConfirmationsError::ApiError
-        })?;
+pub async fn get_confirmations(app: AppHandle) -> Result<Vec<ConfirmationResponse>, SteamError> {
+    with_active_account(app, |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        let confirmations = confirmer.get_confirmations()?;
 
         Ok(confirmations
             .into_iter()
             .map(|x| x.into())
             .collect::<Vec<ConfirmationResponse>>())
     })
-    .await;
-
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            GetConfirmationsError::ApiError
-        })
-        .flatten()
+    .await
 }
 
 #[tauri::command]
 pub async fn get_confirmation_details(
-    state: tauri::State<'_, AppState>,
+    app: AppHandle,
     payload: ConfirmationActionRequest,
-) -> Result<ConfirmationDetailsResponse, GetConfirmationsError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(GetConfirmationsError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-        confirmer
-            .get_confirmation_details(&payload)
-            .map_err(|_| GetConfirmationsError::ApiError)
+) -> Result<ConfirmationDetailsResponse, SteamError> {
+    with_active_account(app, move |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        let html = confirmer.get_confirmation_details(&payload)?;
+        Ok(ConfirmationDetailsResponse { html })
     })
-    .await;
-
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            GetConfirmationsError::ApiError
-        })
-        .flatten()
-        .map(|html| ConfirmationDetailsResponse { html })
+    .await
 }
 
 #[tauri::command]
 pub async fn accept_confirmation(
-    state: tauri::State<'_, AppState>,
+    app: AppHandle,
     payload: ConfirmationActionRequest,
-) -> Result<(), ConfirmationError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(ConfirmationError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-        confirmer
-            .accept_confirmation(&payload)
-            .map_err(|err| match err {
-                ConfirmerError::InvalidTokens => ConfirmationError::Unauthorized,
-                ConfirmerError::DeserializeError(_) => ConfirmationError::DeserializationError,
-                ConfirmerError::NetworkFailure(_) => ConfirmationError::NetworkFailure,
-                _ => ConfirmationError::ApiError,
-            })
+) -> Result<(), SteamError> {
+    with_active_account(app, move |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        Ok(confirmer.accept_confirmation(&payload)?)
     })
-    .await;
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            ConfirmationError::ApiError
-        })
-        .flatten()
+    .await
 }
 
 #[tauri::command]
 pub async fn deny_confirmation(
-    state: tauri::State<'_, AppState>,
+    app: AppHandle,
     payload: ConfirmationActionRequest,
-) -> Result<(), ConfirmationError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(ConfirmationError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-        confirmer
-            .deny_confirmation(&payload)
-            .map_err(|err| match err {
-                ConfirmerError::InvalidTokens => ConfirmationError::Unauthorized,
-                ConfirmerError::DeserializeError(_) => ConfirmationError::DeserializationError,
-                ConfirmerError::NetworkFailure(_) => ConfirmationError::NetworkFailure,
-                _ => ConfirmationError::ApiError,
-            })
+) -> Result<(), SteamError> {
+    with_active_account(app, move |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        Ok(confirmer.deny_confirmation(&payload)?)
     })
-    .await;
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            ConfirmationError::ApiError
-        })
-        .flatten()
+    .await
 }
 
 #[tauri::command]
 pub async fn accept_bulk_confirmations(
-    state: tauri::State<'_, AppState>,
+    app: AppHandle,
     payload: Vec<ConfirmationActionRequest>,
-) -> Result<(), ConfirmationError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(ConfirmationError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-        confirmer
-            .accept_confirmations_bulk(&payload)
-            .map_err(|err| match err {
-                ConfirmerError::InvalidTokens => ConfirmationError::Unauthorized,
-                ConfirmerError::DeserializeError(_) => ConfirmationError::DeserializationError,
-                ConfirmerError::NetworkFailure(_) => ConfirmationError::NetworkFailure,
-                _ => ConfirmationError::ApiError,
-            })
+) -> Result<(), SteamError> {
+    with_active_account(app, move |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        Ok(confirmer.accept_confirmations_bulk(&payload)?)
     })
-    .await;
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            ConfirmationError::ApiError
-        })
-        .flatten()
+    .await
 }
 
 #[tauri::command]
 pub async fn deny_bulk_confirmations(
-    state: tauri::State<'_, AppState>,
+    app: AppHandle,
     payload: Vec<ConfirmationActionRequest>,
-) -> Result<(), ConfirmationError> {
-    let steam_guard_account = state
-        .get_active_steam_guard_account()
-        .ok_or(ConfirmationError::Unauthorized)?;
-    let transport = state.transport.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let confirmer = Confirmer::new(transport, &steam_guard_account);
-        confirmer
-            .deny_confirmations_bulk(&payload)
-            .map_err(|err| match err {
-                ConfirmerError::InvalidTokens => ConfirmationError::Unauthorized,
-                ConfirmerError::DeserializeError(_) => ConfirmationError::DeserializationError,
-                ConfirmerError::NetworkFailure(_) => ConfirmationError::NetworkFailure,
-                _ => ConfirmationError::ApiError,
-            })
+) -> Result<(), SteamError> {
+    with_active_account(app, move |state, steam_guard_account| {
+        let confirmer = Confirmer::new(state.transport.clone(), steam_guard_account);
+        Ok(confirmer.deny_confirmations_bulk(&payload)?)
     })
-    .await;
-    result
-        .map_err(|err| {
-            log::error!("The spawned blocking task panicked or failed to join. {err}");
-            ConfirmationError::ApiError
-        })
-        .flatten()
+    .await
 }
@@ -71,13 +71,6 @@ pub struct ConfirmationDetailsResponse {
     pub html: String,
 }
 
-#[derive(Debug, Serialize)]
-#[serde(tag = "type", content = "message")]
-pub enum GetConfirmationsError {
-    Unauthorized,
-    ApiError,
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfirmationActionRequest {
@@ -90,12 +83,3 @@ impl<'a> From<&'a ConfirmationActionRequest> for ConfirmationId<'a> {
         Self::new(&value.id, &value.nonce)
     }
 }
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum ConfirmationError {
-    Unauthorized,
-    ApiError,
-    DeserializationError,
-    NetworkFailure,
-}
@@ -3,16 +3,32 @@ use std::env;
 use tauri::Manager as _;
 
 use crate::app_state::AppState;
+use crate::auto_lock::activity::track_activity;
+use crate::common::redaction::redact;
 
 mod account;
 mod account_manager;
+mod app_settings;
 mod app_state;
 mod auth;
 mod authentication_approvals;
+mod authenticator;
+mod auto_lock;
+mod avatars;
+mod backup_archive;
 mod common;
+mod config_backups;
+mod config_recovery;
 mod confirmations;
+mod mafiles;
 mod protobufs;
+mod secret_store;
+mod session;
+mod steam_time;
 mod steamapi;
+mod token_refresh;
+mod totp;
+mod vault;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -31,6 +47,16 @@ pub fn run() {
                         .iter()
                         .any(|prefix| metadata.target().starts_with(prefix))
                 })
+                // Every target gets the formatted line, so nothing reaches them unredacted
+                .format(|out, message, record| {
+                    out.finish(format_args!(
+                        "{}[{}][{}] {}",
+                        chrono::Utc::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                        record.level(),
+                        record.target(),
+                        redact(&message.to_string())
+                    ))
+                })
                 // .target(tauri_plugin_log::Target::new(
                 //     tauri_plugin_log::TargetKind::Stdout,
                 // ))
@@ -42,34 +68,54 @@ pub fn run() {
             let state = AppState::init(app);
             {
                 let mut accounts_config = state.accounts_config.lock().unwrap();
-                let current_account = accounts_config.get_active_account_mut();
-                if let Some(account) = current_account {
-                    let has_refreshed = account
-                        .refresh_tokens_if_needed(state.transport.clone())
-                        .expect("Did not implement token refresh failure");
-                    if has_refreshed {
-                        accounts_config
-                            .save_to_config(
-                                &app.path()
-                                    .app_config_dir()
-                                    .expect("Expected access to config directory")
-                                    .join("config.json"),
-                            )
-                            .expect("Failed to save new access token to config");
+                // Done before the window shows up, the scheduler takes care of the rest
+                if let Some(account) = accounts_config.get_active_account_mut() {
+                    match account.refresh_tokens_if_needed(state.transport.clone()) {
+                        Ok(true) => {
+                            if let Err(err) = accounts_config.save_to_config(&state.config_path) {
+                                log::error!("Failed to save new access token to config: {err}");
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(err) => log::warn!("Could not refresh the active account: {err}"),
                     }
                 }
             }
             app.manage(state);
+            steam_time::sync::spawn(app.handle().clone());
+            token_refresh::scheduler::spawn(app.handle().clone());
+            totp::stream::spawn(app.handle().clone());
+            avatars::sync::spawn(app.handle().clone());
+            auto_lock::watcher::spawn(app.handle().clone());
+            backup_archive::scheduler::spawn(app.handle().clone());
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(track_activity(tauri::generate_handler![
             auth::commands::login,
+            auth::commands::submit_login_email_code,
+            auth::commands::poll_login,
+            auth::commands::cancel_login,
+            auth::commands::set_auto_relogin,
             account_manager::commands::is_logged_in,
             account_manager::commands::get_accounts,
             account_manager::commands::get_active_account,
+            account_manager::commands::set_active_account,
+            account_manager::commands::remove_account,
+            account_manager::commands::set_account_alias,
+            account_manager::commands::create_group,
+            account_manager::commands::rename_group,
+            account_manager::commands::delete_group,
+            account_manager::commands::set_account_group,
+            account_manager::commands::set_account_tags,
+            account_manager::commands::set_favourite,
+            account_manager::commands::reorder_accounts,
             account::commands::get_profile,
             authentication_approvals::commands::get_otp,
+            totp::commands::get_code,
+            totp::commands::get_codes,
+            totp::commands::watch_codes,
+            totp::commands::unwatch_codes,
             authentication_approvals::commands::get_sessions,
             authentication_approvals::commands::approve_qr_login,
             authentication_approvals::commands::approve_session,
@@ -80,7 +126,36 @@ pub fn run() {
             confirmations::commands::deny_confirmation,
             confirmations::commands::accept_bulk_confirmations,
             confirmations::commands::deny_bulk_confirmations,
-        ])
+            mafiles::commands::import_mafiles,
+            mafiles::commands::detect_steamguard_cli_directory,
+            mafiles::commands::import_steamguard_cli,
+            mafiles::commands::export_accounts,
+            vault::commands::get_vault_status,
+            vault::commands::unlock_vault,
+            vault::commands::enable_passkey,
+            vault::commands::change_passkey,
+            vault::commands::remove_passkey,
+            vault::commands::lock_vault,
+            secret_store::commands::get_secret_storage,
+            secret_store::commands::set_secret_storage,
+            app_settings::commands::get_settings,
+            app_settings::commands::update_settings,
+            backup_archive::commands::create_backup_archive,
+            backup_archive::commands::preview_backup_archive,
+            backup_archive::commands::restore_backup_archive,
+            config_backups::commands::list_config_backups,
+            config_backups::commands::restore_config_backup,
+            config_recovery::commands::get_recovery_status,
+            config_recovery::commands::salvage_config,
+            authenticator::commands::begin_authenticator_link,
+            authenticator::commands::submit_authenticator_link_email_code,
+            authenticator::commands::set_authenticator_link_phone_number,
+            authenticator::commands::continue_authenticator_link,
+            authenticator::commands::verify_authenticator_link_phone_code,
+            authenticator::commands::finalize_authenticator_link,
+            authenticator::commands::cancel_authenticator_link,
+            authenticator::commands::remove_account_authenticator,
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,196 @@
+use std::io;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+use validator::Validate;
+
+use super::payloads::{
+    ExportAccountsRequest, ExportError, ExportFormat, ExportResponse, ImportError, ImportFailure,
+    ImportMaFilesRequest, ImportResponse, ImportSteamguardCliRequest, ImportedAccount,
+};
+use super::sda::{ImportResult, MANIFEST_FILE_NAME, read_sda_path, write_sda_directory};
+use super::steamguard_cli::{
+    STEAMGUARD_CLI_CONFIG_SUBDIRECTORY, STEAMGUARD_CLI_HOME_SUBDIRECTORY, detect_directory,
+    read_steamguard_cli_directory, write_steamguard_cli_directory,
+};
+use crate::AppState;
+use crate::common::secret::Secret;
+
+#[tauri::command]
+pub async fn import_mafiles(
+    app: AppHandle,
+    payload: ImportMaFilesRequest,
+) -> Result<ImportResponse, ImportError> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let passkey = payload.passkey.as_ref().map(Secret::expose);
+        let results = payload
+            .paths
+            .iter()
+            .flat_map(|path| read_sda_path(&PathBuf::from(path), passkey))
+            .collect();
+        store_imported_accounts(&app.state::<AppState>(), results)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            ImportError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+#[tauri::command]
+pub fn detect_steamguard_cli_directory(app: AppHandle) -> Option<String> {
+    find_steamguard_cli_directory(&app).map(|directory| directory.display().to_string())
+}
+
+#[tauri::command]
+pub async fn import_steamguard_cli(
+    app: AppHandle,
+    payload: ImportSteamguardCliRequest,
+) -> Result<ImportResponse, ImportError> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let directory = payload
+            .directory
+            .map(PathBuf::from)
+            .or_else(|| find_steamguard_cli_directory(&app))
+            .ok_or(ImportError::NotFound)?;
+        let results = read_steamguard_cli_directory(
+            &directory,
+            payload.passkey.as_ref().map(Secret::expose),
+        )?;
+        store_imported_accounts(&app.state::<AppState>(), results)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            ImportError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+#[tauri::command]
+pub async fn export_accounts(
+    app: AppHandle,
+    payload: ExportAccountsRequest,
+) -> Result<ExportResponse, ExportError> {
+    payload.validate()?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let directory = PathBuf::from(&payload.directory);
+        // Never clobber an existing SDA or steamguard-cli setup
+        if directory.join(MANIFEST_FILE_NAME).exists() {
+            return Err(ExportError::ManifestExists);
+        }
+
+        let accounts = {
+            let state = app.state::<AppState>();
+            let accounts_config = state.accounts_config.lock().unwrap();
+            if accounts_config.is_locked() {
+                return Err(ExportError::Locked);
+            }
+            payload
+                .account_names
+                .iter()
+                .map(|account_name| {
+                    accounts_config
+                        .accounts
+                        .iter()
+                        .find(|account| &account.account_name == account_name)
+                        .cloned()
+                        .ok_or_else(|| ExportError::AccountNotFound(account_name.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let passkey = payload.passkey.as_ref().map(Secret::expose);
+        let files = match payload.format {
+            ExportFormat::Sda => write_sda_directory(&directory, &accounts, passkey),
+            ExportFormat::SteamguardCli => {
+                write_steamguard_cli_directory(&directory, &accounts, passkey)
+            }
+        }
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => ExportError::FileExists(err.to_string()),
+            _ => ExportError::IOError(err.to_string()),
+        })?;
+        log::info!(
+            "Exported {} account(s) to {}",
+            accounts.len(),
+            directory.display()
+        );
+
+        Ok(ExportResponse {
+            files: files
+                .into_iter()
+                .map(|file| file.display().to_string())
+                .collect(),
+        })
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            ExportError::IOError(err.to_string())
+        })
+        .flatten()
+}
+
+fn find_steamguard_cli_directory(app: &AppHandle) -> Option<PathBuf> {
+    let path = app.path();
+    let candidates = [
+        path.config_dir()
+            .ok()
+            .map(|dir| dir.join(STEAMGUARD_CLI_CONFIG_SUBDIRECTORY)),
+        path.home_dir()
+            .ok()
+            .map(|dir| dir.join(STEAMGUARD_CLI_HOME_SUBDIRECTORY)),
+    ];
+    detect_directory(&candidates.into_iter().flatten().collect::<Vec<_>>())
+}
+
+/// Saves every successfully read account, reporting the rest as failures
+pub fn store_imported_accounts(
+    state: &AppState,
+    results: Vec<ImportResult>,
+) -> Result<ImportResponse, ImportError> {
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(ImportError::Locked);
+    }
+
+    let mut response = ImportResponse::default();
+    for (source, result) in results {
+        let source = source.display().to_string();
+        match result {
+            Ok(account) => {
+                let account_name = account.account_name.clone();
+                let has_tokens = !account.access_token.is_empty();
+                let replaced = accounts_config.import_account(account);
+                // Accounts without tokens need a login before they can be used
+                if accounts_config.active_account_name.is_none() && has_tokens {
+                    accounts_config.active_account_name = Some(account_name.clone());
+                }
+                response.imported.push(ImportedAccount {
+                    source,
+                    account_name,
+                    replaced,
+                });
+            }
+            Err(error) => {
+                log::debug!("Failed to import {source}: {error:?}");
+                response.failed.push(ImportFailure { source, error });
+            }
+        }
+    }
+
+    if !response.imported.is_empty() {
+        accounts_config
+            .save_to_config(&state.config_path)
+            .map_err(|err| ImportError::IOError(err.to_string()))?;
+    }
+    Ok(response)
+}
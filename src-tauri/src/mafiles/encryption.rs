@@ -0,0 +1,202 @@
+use aes::Aes256;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use zeroize::Zeroizing;
+
+type Aes256CbcEnc = cbc::Encryptor<Aes256>;
+type Aes256CbcDec = cbc::Decryptor<Aes256>;
+
+const KEY_LENGTH: usize = 32;
+const IV_LENGTH: usize = 16;
+/// Same as SDA's `FileEncryptor`
+const PBKDF2_ITERATIONS: u32 = 50_000;
+/// Same as steamguard-cli's `Argon2idAes256`
+const ARGON2_MEMORY_COST: u32 = 7 * 1024;
+const ARGON2_TIME_COST: u32 = 5;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// Both schemes encrypt with AES-256-CBC and only differ in key derivation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncryptionScheme {
+    /// PBKDF2-HMAC-SHA1, used by SDA and older steamguard-cli versions
+    #[default]
+    LegacySdaCompatible,
+    Argon2idAes256,
+}
+
+impl EncryptionScheme {
+    fn salt_length(self) -> usize {
+        match self {
+            EncryptionScheme::LegacySdaCompatible => 8,
+            EncryptionScheme::Argon2idAes256 => 16,
+        }
+    }
+}
+
+/// Base64 encoded, ready to be put into a manifest and maFile
+#[derive(Debug)]
+pub struct EncryptedPayload {
+    pub salt: String,
+    pub iv: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecryptionError {
+    InvalidParams,
+    WrongPasskey,
+}
+
+fn derive_key(
+    scheme: EncryptionScheme,
+    passkey: &str,
+    salt: &[u8],
+) -> Result<Zeroizing<[u8; KEY_LENGTH]>, DecryptionError> {
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    match scheme {
+        EncryptionScheme::LegacySdaCompatible => {
+            pbkdf2::pbkdf2_hmac::<Sha1>(passkey.as_bytes(), salt, PBKDF2_ITERATIONS, key.as_mut());
+        }
+        EncryptionScheme::Argon2idAes256 => {
+            let params = Params::new(
+                ARGON2_MEMORY_COST,
+                ARGON2_TIME_COST,
+                ARGON2_PARALLELISM,
+                Some(KEY_LENGTH),
+            )
+            .expect("Constant Argon2 parameters are valid");
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(passkey.as_bytes(), salt, key.as_mut())
+                .map_err(|_| DecryptionError::InvalidParams)?;
+        }
+    }
+    Ok(key)
+}
+
+pub fn encrypt(scheme: EncryptionScheme, passkey: &str, plaintext: &[u8]) -> EncryptedPayload {
+    let mut salt = vec![0u8; scheme.salt_length()];
+    let mut iv = [0u8; IV_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut iv);
+
+    let key = derive_key(scheme, passkey, &salt).expect("Generated salt has a valid length");
+    let ciphertext = Aes256CbcEnc::new_from_slices(key.as_ref(), &iv)
+        .expect("Key and IV have valid lengths")
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+    EncryptedPayload {
+        salt: STANDARD.encode(salt),
+        iv: STANDARD.encode(iv),
+        ciphertext: STANDARD.encode(ciphertext),
+    }
+}
+
+/// All inputs are base64 as stored in the manifest and maFile
+pub fn decrypt(
+    scheme: EncryptionScheme,
+    passkey: &str,
+    salt: &str,
+    iv: &str,
+    ciphertext: &str,
+) -> Result<Zeroizing<Vec<u8>>, DecryptionError> {
+    let salt = STANDARD
+        .decode(salt)
+        .map_err(|_| DecryptionError::InvalidParams)?;
+    let iv = STANDARD
+        .decode(iv)
+        .map_err(|_| DecryptionError::InvalidParams)?;
+    let ciphertext = STANDARD
+        .decode(ciphertext.trim())
+        .map_err(|_| DecryptionError::InvalidParams)?;
+
+    let key = derive_key(scheme, passkey, &salt)?;
+    Aes256CbcDec::new_from_slices(key.as_ref(), &iv)
+        .map_err(|_| DecryptionError::InvalidParams)?
+        .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext)
+        .map(Zeroizing::new)
+        // A wrong key almost always shows up as broken padding
+        .map_err(|_| DecryptionError::WrongPasskey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT: &str = "c2FsdHNhbHQ=";
+    const IV: &str = "AAECAwQFBgcICQoLDA0ODw==";
+    /// `{"account_name":"matcha_latte"}` encrypted by SDA's scheme with the passkey "hunter2"
+    const CIPHERTEXT: &str = "kK24i/q6lqtSNyQ4MJuNPMLjPS87cVLFGypysn729cc=";
+
+    #[test]
+    fn decrypts_sda_encrypted_payload() {
+        let plaintext = decrypt(
+            EncryptionScheme::LegacySdaCompatible,
+            "hunter2",
+            SALT,
+            IV,
+            CIPHERTEXT,
+        )
+        .unwrap();
+        assert_eq!(plaintext.as_slice(), br#"{"account_name":"matcha_latte"}"#);
+    }
+
+    #[test]
+    fn fails_decryption_with_wrong_passkey() {
+        assert!(
+            decrypt(
+                EncryptionScheme::LegacySdaCompatible,
+                "hunter3",
+                SALT,
+                IV,
+                CIPHERTEXT
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn fails_decryption_with_invalid_iv() {
+        assert_eq!(
+            decrypt(
+                EncryptionScheme::LegacySdaCompatible,
+                "hunter2",
+                SALT,
+                "AAEC",
+                CIPHERTEXT
+            )
+            .unwrap_err(),
+            DecryptionError::InvalidParams
+        );
+    }
+
+    #[test]
+    fn decrypts_own_encrypted_payload() {
+        for scheme in [
+            EncryptionScheme::LegacySdaCompatible,
+            EncryptionScheme::Argon2idAes256,
+        ] {
+            let payload = encrypt(scheme, "hunter2", b"shared_secret");
+            let plaintext = decrypt(
+                scheme,
+                "hunter2",
+                &payload.salt,
+                &payload.iv,
+                &payload.ciphertext,
+            )
+            .unwrap();
+            assert_eq!(plaintext.as_slice(), b"shared_secret");
+        }
+    }
+
+    #[test]
+    fn deserializes_steamguard_cli_scheme_names() {
+        let scheme: EncryptionScheme = serde_json::from_str("\"Argon2idAes256\"").unwrap();
+        assert_eq!(scheme, EncryptionScheme::Argon2idAes256);
+    }
+}
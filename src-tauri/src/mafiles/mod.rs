@@ -0,0 +1,5 @@
+pub mod commands;
+mod encryption;
+pub mod payloads;
+pub mod sda;
+pub mod steamguard_cli;
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use super::encryption::DecryptionError;
+use crate::common::secret::Secret;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMaFilesRequest {
+    /// Directories containing a `manifest.json` or `.maFile`s, or individual files
+    pub paths: Vec<String>,
+    /// Required if the manifest marks the maFiles as encrypted
+    pub passkey: Option<Secret>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSteamguardCliRequest {
+    /// Detected automatically when omitted
+    pub directory: Option<String>,
+    /// Required if the manifest has encryption parameters for any entry
+    pub passkey: Option<Secret>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    Sda,
+    SteamguardCli,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportAccountsRequest {
+    #[validate(length(min = 1))]
+    pub account_names: Vec<String>,
+    pub format: ExportFormat,
+    /// Must not already contain a `manifest.json`
+    pub directory: String,
+    /// Encrypts the exported maFiles if provided
+    #[validate(length(min = 1))]
+    pub passkey: Option<Secret>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResponse {
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum ExportError {
+    Locked,
+    AccountNotFound(String),
+    ManifestExists,
+    /// An export would replace this file
+    FileExists(String),
+    ValidationError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for ExportError {
+    fn from(value: ValidationErrors) -> Self {
+        ExportError::ValidationError(value.to_string())
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResponse {
+    pub imported: Vec<ImportedAccount>,
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedAccount {
+    pub source: String,
+    pub account_name: String,
+    /// An account with the same name already existed and was overwritten
+    pub replaced: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFailure {
+    pub source: String,
+    pub error: ImportError,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum ImportError {
+    Locked,
+    NotFound,
+    PasskeyRequired,
+    WrongPasskey,
+    InvalidFormat(String),
+    InvalidSecret,
+    MissingSteamId,
+    IOError(String),
+}
+
+impl From<DecryptionError> for ImportError {
+    fn from(value: DecryptionError) -> Self {
+        match value {
+            DecryptionError::InvalidParams => {
+                ImportError::InvalidFormat("Invalid encryption parameters".to_string())
+            }
+            DecryptionError::WrongPasskey => ImportError::WrongPasskey,
+        }
+    }
+}
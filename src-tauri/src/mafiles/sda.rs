@@ -0,0 +1,502 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, PickFirst, serde_as};
+
+use super::encryption::{EncryptionScheme, decrypt, encrypt};
+use super::payloads::ImportError;
+use crate::account_manager::account_files::file_stem;
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::secret::Secret;
+use crate::common::validators::validate_steam_secret;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+pub const MAFILE_EXTENSION: &str = "maFile";
+
+/// Account file as written by Steam Desktop Authenticator
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MaFile {
+    pub shared_secret: Secret,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    #[serde(default)]
+    pub revocation_code: Option<Secret>,
+    #[serde(default)]
+    pub uri: Option<Secret>,
+    #[serde(default)]
+    pub server_time: Option<u64>,
+    pub account_name: String,
+    #[serde(default)]
+    pub token_gid: Option<String>,
+    pub identity_secret: Secret,
+    #[serde(default)]
+    pub secret_1: Option<Secret>,
+    #[serde(default)]
+    pub status: Option<i32>,
+    #[serde(default)]
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub fully_enrolled: Option<bool>,
+    #[serde(rename = "Session", default)]
+    pub session: Option<MaFileSession>,
+}
+
+/// Older SDA versions stored cookies here, newer ones store the JWT pair
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MaFileSession {
+    #[serde(rename = "SteamID")]
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub steam_id: u64,
+    #[serde(default)]
+    pub access_token: Option<Secret>,
+    #[serde(default)]
+    pub refresh_token: Option<Secret>,
+    #[serde(rename = "SessionID", default)]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SdaManifest {
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub entries: Vec<SdaManifestEntry>,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SdaManifestEntry {
+    pub encryption_iv: Option<String>,
+    pub encryption_salt: Option<String>,
+    pub filename: String,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub steamid: u64,
+}
+
+impl MaFile {
+    pub fn into_credentials(
+        self,
+        fallback_steam_id: Option<u64>,
+    ) -> Result<UserCredentials, ImportError> {
+        validate_steam_secret(&self.shared_secret).map_err(|_| ImportError::InvalidSecret)?;
+        validate_steam_secret(&self.identity_secret).map_err(|_| ImportError::InvalidSecret)?;
+        let session = self.session.unwrap_or_default();
+        let steam_id = Some(session.steam_id)
+            .filter(|steam_id| *steam_id != 0)
+            .or(fallback_steam_id)
+            .ok_or(ImportError::MissingSteamId)?;
+
+        Ok(UserCredentials {
+            steam_id,
+            account_name: self.account_name,
+            shared_secret: self.shared_secret,
+            identity_secret: self.identity_secret,
+            device_id: self.device_id.unwrap_or_default(),
+            revocation_code: self.revocation_code,
+            secret_1: self.secret_1,
+            serial_number: self.serial_number.filter(|serial| !serial.is_empty()),
+            access_token: session.access_token.unwrap_or_default(),
+            refresh_token: session.refresh_token.unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+}
+
+/// SDA refuses files with missing non-nullable fields, so those get explicit defaults
+impl From<&UserCredentials> for MaFile {
+    fn from(value: &UserCredentials) -> Self {
+        Self {
+            shared_secret: value.shared_secret.clone(),
+            serial_number: Some(value.serial_number.clone().unwrap_or_default()),
+            revocation_code: value.revocation_code.clone(),
+            uri: Some(Secret::default()),
+            server_time: Some(0),
+            account_name: value.account_name.clone(),
+            token_gid: Some(String::new()),
+            identity_secret: value.identity_secret.clone(),
+            secret_1: value.secret_1.clone(),
+            status: Some(1),
+            device_id: Some(value.device_id.clone()),
+            fully_enrolled: Some(true),
+            session: Some(MaFileSession {
+                steam_id: value.steam_id,
+                access_token: Some(value.access_token.clone()).filter(|token| !token.is_empty()),
+                refresh_token: Some(value.refresh_token.clone()).filter(|token| !token.is_empty()),
+                session_id: None,
+            }),
+        }
+    }
+}
+
+/// Writes `<steam_id>.maFile` for every account plus a `manifest.json`, returning the written paths.
+/// Accounts without a Steam ID are named after the account. Existing files are never replaced.
+pub fn write_sda_directory(
+    directory: &Path,
+    accounts: &[UserCredentials],
+    passkey: Option<&str>,
+) -> io::Result<Vec<PathBuf>> {
+    let filenames = accounts
+        .iter()
+        .map(|account| match account.steam_id {
+            0 => mafile_name(&account.account_name),
+            steam_id => format!("{steam_id}.{MAFILE_EXTENSION}"),
+        })
+        .collect::<Vec<_>>();
+    ensure_new_files(directory, &filenames)?;
+    fs::create_dir_all(directory)?;
+    let mut manifest = SdaManifest {
+        encrypted: passkey.is_some(),
+        entries: Vec::with_capacity(accounts.len()),
+    };
+    let mut written = Vec::with_capacity(accounts.len() + 1);
+
+    for (account, filename) in accounts.iter().zip(filenames) {
+        let content = serde_json::to_string(&MaFile::from(account))?;
+        let mut entry = SdaManifestEntry {
+            filename,
+            steamid: account.steam_id,
+            ..Default::default()
+        };
+        let content = match passkey {
+            Some(passkey) => {
+                let payload = encrypt(
+                    EncryptionScheme::LegacySdaCompatible,
+                    passkey,
+                    content.as_bytes(),
+                );
+                entry.encryption_iv = Some(payload.iv);
+                entry.encryption_salt = Some(payload.salt);
+                payload.ciphertext
+            }
+            None => content,
+        };
+        let mafile_path = directory.join(&entry.filename);
+        write_new_file(&mafile_path, content.as_bytes())?;
+        written.push(mafile_path);
+        manifest.entries.push(entry);
+    }
+
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+    write_new_file(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+    written.push(manifest_path);
+    Ok(written)
+}
+
+/// `<account_name>.maFile`, encoded like the app's own account files so an imported name
+/// cannot point outside the export directory
+pub fn mafile_name(account_name: &str) -> String {
+    format!("{}.{}", file_stem(account_name), MAFILE_EXTENSION)
+}
+
+/// Fails with [`io::ErrorKind::AlreadyExists`] before anything is written, so an export never
+/// leaves a mix of old and new files behind
+pub fn ensure_new_files(directory: &Path, filenames: &[String]) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    for filename in filenames
+        .iter()
+        .map(String::as_str)
+        .chain([MANIFEST_FILE_NAME])
+    {
+        let path = directory.join(filename);
+        if !seen.insert(filename) || path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                path.display().to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Like [`fs::write`], but refuses to replace an existing file
+pub fn write_new_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents)
+}
+
+pub type ImportResult = (PathBuf, Result<UserCredentials, ImportError>);
+
+/// Reads a directory (with or without a manifest), a `manifest.json` or a single maFile.
+/// Each maFile gets its own result, so one broken file doesn't abort the rest.
+pub fn read_sda_path(path: &Path, passkey: Option<&str>) -> Vec<ImportResult> {
+    if path.is_dir() {
+        let manifest_path = path.join(MANIFEST_FILE_NAME);
+        if manifest_path.exists() {
+            return read_manifest(&manifest_path, passkey);
+        }
+        return match list_mafiles(path) {
+            Ok(mafile_paths) => mafile_paths
+                .into_iter()
+                .map(|mafile_path| {
+                    let result = read_mafile(&mafile_path, None, passkey);
+                    (mafile_path, result)
+                })
+                .collect(),
+            Err(err) => vec![(path.to_path_buf(), Err(err))],
+        };
+    }
+    if path
+        .file_name()
+        .is_some_and(|name| name == MANIFEST_FILE_NAME)
+    {
+        return read_manifest(path, passkey);
+    }
+
+    // A single maFile might still be encrypted, in which case the IV and salt live in the manifest next to it
+    let manifest = path
+        .parent()
+        .map(|parent| parent.join(MANIFEST_FILE_NAME))
+        .filter(|manifest_path| manifest_path.exists())
+        .and_then(|manifest_path| parse_manifest(&manifest_path).ok());
+    let entry = manifest.as_ref().and_then(|manifest| {
+        manifest.entries.iter().find(|entry| {
+            path.file_name()
+                .is_some_and(|name| name == entry.filename.as_str())
+        })
+    });
+    let result = read_mafile(path, entry, passkey);
+    vec![(path.to_path_buf(), result)]
+}
+
+fn read_manifest(manifest_path: &Path, passkey: Option<&str>) -> Vec<ImportResult> {
+    let manifest = match parse_manifest(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(err) => return vec![(manifest_path.to_path_buf(), Err(err))],
+    };
+    let directory = manifest_path.parent().unwrap_or(Path::new("."));
+    manifest
+        .entries
+        .iter()
+        .map(|entry| {
+            let mafile_path = directory.join(&entry.filename);
+            let result = read_mafile(&mafile_path, Some(entry), passkey);
+            (mafile_path, result)
+        })
+        .collect()
+}
+
+fn parse_manifest(manifest_path: &Path) -> Result<SdaManifest, ImportError> {
+    let content = read_file(manifest_path)?;
+    serde_json::from_str(&content).map_err(|err| ImportError::InvalidFormat(err.to_string()))
+}
+
+fn list_mafiles(directory: &Path) -> Result<Vec<PathBuf>, ImportError> {
+    let mut paths = fs::read_dir(directory)
+        .map_err(|err| ImportError::IOError(err.to_string()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == MAFILE_EXTENSION))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_mafile(
+    mafile_path: &Path,
+    entry: Option<&SdaManifestEntry>,
+    passkey: Option<&str>,
+) -> Result<UserCredentials, ImportError> {
+    let content = read_file(mafile_path)?;
+    let encryption = entry.and_then(|entry| {
+        entry
+            .encryption_iv
+            .as_deref()
+            .zip(entry.encryption_salt.as_deref())
+    });
+    let mafile: MaFile = match encryption {
+        Some((iv, salt)) => {
+            let passkey = passkey.ok_or(ImportError::PasskeyRequired)?;
+            let plaintext = decrypt(
+                EncryptionScheme::LegacySdaCompatible,
+                passkey,
+                salt,
+                iv,
+                &content,
+            )?;
+            serde_json::from_slice(&plaintext)
+        }
+        None => serde_json::from_str(&content),
+    }
+    .map_err(|err| ImportError::InvalidFormat(err.to_string()))?;
+
+    mafile.into_credentials(entry.map(|entry| entry.steamid))
+}
+
+pub fn read_file(path: &Path) -> Result<String, ImportError> {
+    if !path.exists() {
+        return Err(ImportError::NotFound);
+    }
+    fs::read_to_string(path).map_err(|err| ImportError::IOError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+
+    #[test]
+    fn maps_sda_mafile_onto_credentials() {
+        let json = json!({
+            "shared_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "serial_number": "1234567890123456789",
+            "revocation_code": "R12345",
+            "uri": "otpauth://totp/Steam:matcha_latte?secret=CWTDNS3NQOGAY6R76TVJGVXKFFLUQ&issuer=Steam",
+            "server_time": 1700000000,
+            "account_name": "matcha_latte",
+            "token_gid": "2a1e5e1d4b2b3c4d",
+            "identity_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "secret_1": "c2VjcmV0X29uZQ==",
+            "status": 1,
+            "device_id": "android:0f9a51d4-86e1-2af6-1ae8-8f23c3a4d5e6",
+            "fully_enrolled": true,
+            "Session": {
+                "SessionID": "abc",
+                "SteamID": 76561198000000000u64,
+                "AccessToken": "access",
+                "RefreshToken": "refresh"
+            }
+        });
+        let mafile: MaFile = serde_json::from_value(json).unwrap();
+        let credentials = mafile.into_credentials(None).unwrap();
+
+        assert_eq!(credentials.steam_id, 76561198000000000);
+        assert_eq!(credentials.account_name, "matcha_latte");
+        assert_eq!(credentials.revocation_code, Some("R12345".into()));
+        assert_eq!(credentials.secret_1, Some("c2VjcmV0X29uZQ==".into()));
+        assert_eq!(
+            credentials.device_id,
+            "android:0f9a51d4-86e1-2af6-1ae8-8f23c3a4d5e6"
+        );
+        assert_eq!(credentials.access_token.expose(), "access");
+        assert_eq!(credentials.refresh_token.expose(), "refresh");
+    }
+
+    #[test]
+    fn falls_back_to_manifest_steam_id_without_session() {
+        let json = json!({
+            "shared_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "account_name": "matcha_latte",
+            "identity_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+        });
+        let mafile: MaFile = serde_json::from_value(json).unwrap();
+        let credentials = mafile.into_credentials(Some(76561198000000001)).unwrap();
+
+        assert_eq!(credentials.steam_id, 76561198000000001);
+        assert!(credentials.access_token.is_empty());
+    }
+
+    #[test]
+    fn rejects_mafile_with_invalid_shared_secret() {
+        let json = json!({
+            "shared_secret": "~~~~",
+            "account_name": "matcha_latte",
+            "identity_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "Session": { "SteamID": "76561198000000000" }
+        });
+        let mafile: MaFile = serde_json::from_value(json).unwrap();
+
+        assert!(matches!(
+            mafile.into_credentials(None),
+            Err(ImportError::InvalidSecret)
+        ));
+    }
+
+    #[test]
+    fn round_trips_credentials_through_mafile() {
+        let credentials = UserCredentials {
+            steam_id: 76561198000000000,
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            revocation_code: Some("R12345".into()),
+            access_token: "access".into(),
+            refresh_token: "refresh".into(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(MaFile::from(&credentials)).unwrap();
+        assert_eq!(json["Session"]["SteamID"], 76561198000000000u64);
+
+        let parsed = serde_json::from_value::<MaFile>(json)
+            .unwrap()
+            .into_credentials(None)
+            .unwrap();
+        assert_eq!(parsed.account_name, credentials.account_name);
+        assert_eq!(parsed.revocation_code, credentials.revocation_code);
+        assert_eq!(parsed.refresh_token, credentials.refresh_token);
+    }
+
+    #[test]
+    fn parses_encrypted_manifest_entries() {
+        let json = json!({
+            "encrypted": true,
+            "first_run": false,
+            "entries": [{
+                "encryption_iv": "AAECAwQFBgcICQoLDA0ODw==",
+                "encryption_salt": "c2FsdHNhbHQ=",
+                "filename": "76561198000000000.maFile",
+                "steamid": 76561198000000000u64
+            }],
+            "periodic_checking": false
+        });
+        let manifest: SdaManifest = serde_json::from_value(json).unwrap();
+
+        assert!(manifest.encrypted);
+        assert_eq!(manifest.entries[0].steamid, 76561198000000000);
+        assert_eq!(
+            manifest.entries[0].encryption_salt.as_deref(),
+            Some("c2FsdHNhbHQ=")
+        );
+    }
+
+    #[test]
+    fn names_accounts_without_steam_id_after_the_account() {
+        let directory = unique_test_dir("sda-export-names");
+        let accounts = ["matcha_latte", "chai_latte"].map(|account_name| UserCredentials {
+            account_name: account_name.to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        });
+        let written = write_sda_directory(&directory, &accounts, None).unwrap();
+
+        let results = read_sda_path(&directory, None);
+        let _ = fs::remove_dir_all(&directory);
+        assert_eq!(written[0], directory.join("matcha_latte.maFile"));
+        assert_eq!(written[1], directory.join("chai_latte.maFile"));
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn refuses_to_replace_existing_mafiles() {
+        let directory = unique_test_dir("sda-export-existing");
+        let existing = directory.join("76561198000000000.maFile");
+        fs::write(&existing, "keep me").unwrap();
+        let account = UserCredentials {
+            steam_id: 76561198000000000,
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+
+        let result = write_sda_directory(&directory, &[account], None);
+        let content = fs::read_to_string(&existing).unwrap();
+        let files = fs::read_dir(&directory).unwrap().count();
+        let _ = fs::remove_dir_all(&directory);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(content, "keep me");
+        assert_eq!(files, 1);
+    }
+}
@@ -0,0 +1,362 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, PickFirst, serde_as};
+
+use super::encryption::{EncryptionScheme, decrypt, encrypt};
+use super::payloads::ImportError;
+use super::sda::{
+    ImportResult, MANIFEST_FILE_NAME, MaFile, ensure_new_files, mafile_name, read_file,
+    write_new_file,
+};
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::secret::Secret;
+use crate::common::validators::validate_steam_secret;
+
+/// Relative to the OS config directory, e.g. `~/.config/steamguard-cli/maFiles`
+pub const STEAMGUARD_CLI_CONFIG_SUBDIRECTORY: &str = "steamguard-cli/maFiles";
+/// Fallback location steamguard-cli also looks at
+pub const STEAMGUARD_CLI_HOME_SUBDIRECTORY: &str = "maFiles";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SteamguardCliManifest {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub entries: Vec<SteamguardCliManifestEntry>,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SteamguardCliManifestEntry {
+    pub filename: String,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub steam_id: u64,
+    #[serde(default)]
+    pub account_name: String,
+    #[serde(default)]
+    pub encryption: Option<SteamguardCliEncryptionParams>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SteamguardCliEncryptionParams {
+    pub iv: String,
+    pub salt: String,
+    #[serde(default)]
+    pub scheme: EncryptionScheme,
+}
+
+/// The serialized `SteamGuardAccount` written by current steamguard-cli versions
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SteamguardCliAccount {
+    pub account_name: String,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub steam_id: u64,
+    #[serde(default)]
+    pub serial_number: String,
+    #[serde(default)]
+    pub revocation_code: Secret,
+    pub shared_secret: Secret,
+    #[serde(default)]
+    pub token_gid: String,
+    pub identity_secret: Secret,
+    #[serde(default)]
+    pub uri: Secret,
+    #[serde(default)]
+    pub device_id: String,
+    #[serde(default)]
+    pub secret_1: Secret,
+    #[serde(default)]
+    pub tokens: Option<SteamguardCliTokens>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SteamguardCliTokens {
+    pub access_token: Secret,
+    pub refresh_token: Secret,
+}
+
+/// steamguard-cli still reads SDA formatted account files, so both have to be accepted
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SteamguardCliAccountFile {
+    Current(SteamguardCliAccount),
+    Legacy(MaFile),
+}
+
+impl SteamguardCliAccount {
+    pub fn into_credentials(self) -> Result<UserCredentials, ImportError> {
+        validate_steam_secret(&self.shared_secret).map_err(|_| ImportError::InvalidSecret)?;
+        validate_steam_secret(&self.identity_secret).map_err(|_| ImportError::InvalidSecret)?;
+        let tokens = self.tokens.unwrap_or_default();
+
+        Ok(UserCredentials {
+            steam_id: self.steam_id,
+            account_name: self.account_name,
+            shared_secret: self.shared_secret,
+            identity_secret: self.identity_secret,
+            device_id: self.device_id,
+            revocation_code: Some(self.revocation_code).filter(|code| !code.is_empty()),
+            secret_1: Some(self.secret_1).filter(|secret| !secret.is_empty()),
+            serial_number: Some(self.serial_number).filter(|serial| !serial.is_empty()),
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            ..Default::default()
+        })
+    }
+}
+
+impl From<&UserCredentials> for SteamguardCliAccount {
+    fn from(value: &UserCredentials) -> Self {
+        Self {
+            account_name: value.account_name.clone(),
+            steam_id: value.steam_id,
+            revocation_code: value.revocation_code.clone().unwrap_or_default(),
+            shared_secret: value.shared_secret.clone(),
+            identity_secret: value.identity_secret.clone(),
+            device_id: value.device_id.clone(),
+            secret_1: value.secret_1.clone().unwrap_or_default(),
+            serial_number: value.serial_number.clone().unwrap_or_default(),
+            tokens: Some(SteamguardCliTokens {
+                access_token: value.access_token.clone(),
+                refresh_token: value.refresh_token.clone(),
+            })
+            .filter(|tokens| !tokens.refresh_token.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Writes `<account_name>.maFile` for every account plus a version 1 `manifest.json`.
+/// Encryption uses the legacy scheme, which every steamguard-cli version can read.
+/// Existing files are never replaced.
+pub fn write_steamguard_cli_directory(
+    directory: &Path,
+    accounts: &[UserCredentials],
+    passkey: Option<&str>,
+) -> io::Result<Vec<PathBuf>> {
+    let filenames = accounts
+        .iter()
+        .map(|account| mafile_name(&account.account_name))
+        .collect::<Vec<_>>();
+    ensure_new_files(directory, &filenames)?;
+    fs::create_dir_all(directory)?;
+    let mut manifest = SteamguardCliManifest {
+        version: 1,
+        entries: Vec::with_capacity(accounts.len()),
+    };
+    let mut written = Vec::with_capacity(accounts.len() + 1);
+
+    for (account, filename) in accounts.iter().zip(filenames) {
+        let mut entry = SteamguardCliManifestEntry {
+            filename,
+            steam_id: account.steam_id,
+            account_name: account.account_name.clone(),
+            encryption: None,
+        };
+        let content = serde_json::to_string_pretty(&SteamguardCliAccount::from(account))?;
+        let content = match passkey {
+            Some(passkey) => {
+                let scheme = EncryptionScheme::LegacySdaCompatible;
+                let payload = encrypt(scheme, passkey, content.as_bytes());
+                entry.encryption = Some(SteamguardCliEncryptionParams {
+                    iv: payload.iv,
+                    salt: payload.salt,
+                    scheme,
+                });
+                payload.ciphertext
+            }
+            None => content,
+        };
+        let account_path = directory.join(&entry.filename);
+        write_new_file(&account_path, content.as_bytes())?;
+        written.push(account_path);
+        manifest.entries.push(entry);
+    }
+
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+    write_new_file(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+    written.push(manifest_path);
+    Ok(written)
+}
+
+/// Returns the first candidate directory that contains a steamguard-cli manifest
+pub fn detect_directory(candidates: &[PathBuf]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .find(|candidate| candidate.join(MANIFEST_FILE_NAME).is_file())
+        .cloned()
+}
+
+/// Reads every account listed in the manifest. Failures are reported per account.
+pub fn read_steamguard_cli_directory(
+    directory: &Path,
+    passkey: Option<&str>,
+) -> Result<Vec<ImportResult>, ImportError> {
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+    let manifest: SteamguardCliManifest = serde_json::from_str(&read_file(&manifest_path)?)
+        .map_err(|err| ImportError::InvalidFormat(err.to_string()))?;
+
+    Ok(manifest
+        .entries
+        .iter()
+        .map(|entry| {
+            let account_path = directory.join(&entry.filename);
+            let result = read_account(&account_path, entry, passkey);
+            (account_path, result)
+        })
+        .collect())
+}
+
+fn read_account(
+    account_path: &Path,
+    entry: &SteamguardCliManifestEntry,
+    passkey: Option<&str>,
+) -> Result<UserCredentials, ImportError> {
+    let content = read_file(account_path)?;
+    let account_file: SteamguardCliAccountFile = match &entry.encryption {
+        Some(params) => {
+            let passkey = passkey.ok_or(ImportError::PasskeyRequired)?;
+            let plaintext = decrypt(params.scheme, passkey, &params.salt, &params.iv, &content)?;
+            serde_json::from_slice(&plaintext)
+        }
+        None => serde_json::from_str(&content),
+    }
+    .map_err(|err| ImportError::InvalidFormat(err.to_string()))?;
+
+    match account_file {
+        SteamguardCliAccountFile::Current(account) => account.into_credentials(),
+        SteamguardCliAccountFile::Legacy(mafile) => mafile.into_credentials(Some(entry.steam_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::test_dir::unique_test_dir;
+
+    #[test]
+    fn parses_manifest_with_encryption_params() {
+        let json = json!({
+            "version": 1,
+            "entries": [{
+                "filename": "matcha_latte.maFile",
+                "steam_id": 76561198000000000u64,
+                "account_name": "matcha_latte",
+                "encryption": {
+                    "iv": "AAECAwQFBgcICQoLDA0ODw==",
+                    "salt": "c2FsdHNhbHQ=",
+                    "scheme": "Argon2idAes256"
+                }
+            }, {
+                "filename": "chai_latte.maFile",
+                "steam_id": "76561198000000001",
+                "account_name": "chai_latte",
+                "encryption": null
+            }],
+            "keyring_id": null,
+            "auto_confirm_market_transactions": false,
+            "auto_confirm_trades": false
+        });
+        let manifest: SteamguardCliManifest = serde_json::from_value(json).unwrap();
+
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(
+            manifest.entries[0].encryption.as_ref().unwrap().scheme,
+            EncryptionScheme::Argon2idAes256
+        );
+        assert_eq!(manifest.entries[1].steam_id, 76561198000000001);
+        assert!(manifest.entries[1].encryption.is_none());
+    }
+
+    #[test]
+    fn reads_current_account_format() {
+        let json = json!({
+            "account_name": "matcha_latte",
+            "steam_id": 76561198000000000u64,
+            "serial_number": "1234567890123456789",
+            "revocation_code": "R12345",
+            "shared_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "token_gid": "2a1e5e1d4b2b3c4d",
+            "identity_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "uri": "otpauth://totp/Steam:matcha_latte?secret=CWTDNS3NQOGAY6R76TVJGVXKFFLUQ&issuer=Steam",
+            "device_id": "android:0f9a51d4-86e1-2af6-1ae8-8f23c3a4d5e6",
+            "secret_1": "c2VjcmV0X29uZQ==",
+            "tokens": {
+                "access_token": "access",
+                "refresh_token": "refresh"
+            }
+        });
+        let account_file: SteamguardCliAccountFile = serde_json::from_value(json).unwrap();
+        let SteamguardCliAccountFile::Current(account) = account_file else {
+            panic!("Expected the current steamguard-cli format");
+        };
+        let credentials = account.into_credentials().unwrap();
+
+        assert_eq!(credentials.steam_id, 76561198000000000);
+        assert_eq!(credentials.revocation_code, Some("R12345".into()));
+        assert_eq!(credentials.refresh_token.expose(), "refresh");
+    }
+
+    #[test]
+    fn reads_back_exported_encrypted_directory() {
+        let directory = unique_test_dir("steamguard-cli-export");
+        let credentials = UserCredentials {
+            steam_id: 76561198000000000,
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+        write_steamguard_cli_directory(&directory, &[credentials], Some("hunter2")).unwrap();
+
+        let results = read_steamguard_cli_directory(&directory, Some("hunter2")).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().unwrap().account_name, "matcha_latte");
+    }
+
+    #[test]
+    fn falls_back_to_sda_account_format() {
+        let json = json!({
+            "shared_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "account_name": "matcha_latte",
+            "identity_secret": "FSY2y2mThnpJv1h+lXKTVuH+cvQ=",
+            "Session": { "SteamID": 76561198000000000u64 }
+        });
+        let account_file: SteamguardCliAccountFile = serde_json::from_value(json).unwrap();
+
+        assert!(matches!(account_file, SteamguardCliAccountFile::Legacy(_)));
+    }
+
+    #[test]
+    fn keeps_exported_files_inside_the_directory() {
+        let directory = unique_test_dir("steamguard-cli-export-names");
+        let credentials = UserCredentials {
+            account_name: "../../matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            identity_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            ..Default::default()
+        };
+        let written = write_steamguard_cli_directory(&directory, &[credentials], None).unwrap();
+
+        let results = read_steamguard_cli_directory(&directory, None).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+        assert!(
+            written
+                .iter()
+                .all(|path| path.parent() == Some(directory.as_path()))
+        );
+        assert_eq!(
+            results[0].1.as_ref().unwrap().account_name,
+            "../../matcha_latte"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use sha1::{Digest as _, Sha1};
+
+use super::store::{SecretStore, SecretStoreError};
+
+/// Remembers a digest of every secret read from or written to `S`, so saving an account whose
+/// secrets did not change touches the platform store not at all. Every keychain access may
+/// show a prompt, and the whole config is saved on each reorder or tag change.
+#[derive(Debug)]
+pub struct CachedStore<S> {
+    store: S,
+    known: Mutex<HashMap<String, [u8; 20]>>,
+}
+
+impl<S: SecretStore> CachedStore<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            known: Mutex::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    fn remember(&self, account_name: &str, secret: Option<&str>) {
+        let mut known = self.known.lock().unwrap();
+        match secret {
+            // A digest only, the cache must not become another copy of the secrets
+            Some(secret) => known.insert(account_name.to_string(), Sha1::digest(secret).into()),
+            None => known.remove(account_name),
+        };
+    }
+}
+
+impl<S: SecretStore> SecretStore for CachedStore<S> {
+    fn get(&self, account_name: &str) -> Result<Option<String>, SecretStoreError> {
+        let secret = self.store.get(account_name)?;
+        self.remember(account_name, secret.as_deref());
+        Ok(secret)
+    }
+
+    fn set(&self, account_name: &str, secret: &str) -> Result<(), SecretStoreError> {
+        let digest: [u8; 20] = Sha1::digest(secret).into();
+        if self.known.lock().unwrap().get(account_name) == Some(&digest) {
+            return Ok(());
+        }
+        // Unknown until the write went through
+        self.remember(account_name, None);
+        self.store.set(account_name, secret)?;
+        self.remember(account_name, Some(secret));
+        Ok(())
+    }
+
+    fn delete(&self, account_name: &str) -> Result<(), SecretStoreError> {
+        self.remember(account_name, None);
+        self.store.delete(account_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_store::memory_store::MemoryStore;
+
+    #[test]
+    fn writes_only_changed_secrets() {
+        let secret_store = CachedStore::new(MemoryStore::default());
+
+        secret_store.set("matcha_latte", "secret").unwrap();
+        secret_store.set("matcha_latte", "secret").unwrap();
+        secret_store.set("matcha_latte", "rotated").unwrap();
+
+        assert_eq!(secret_store.inner().writes(), 2);
+        assert_eq!(secret_store.inner().reads(), 0);
+    }
+
+    #[test]
+    fn writes_again_after_a_delete() {
+        let secret_store = CachedStore::new(MemoryStore::default());
+
+        secret_store.set("matcha_latte", "secret").unwrap();
+        secret_store.delete("matcha_latte").unwrap();
+        secret_store.set("matcha_latte", "secret").unwrap();
+
+        assert!(secret_store.inner().contains("matcha_latte"));
+        assert_eq!(secret_store.inner().writes(), 2);
+    }
+
+    #[test]
+    fn skips_secrets_it_read() {
+        let memory_store = MemoryStore::default();
+        memory_store.set("matcha_latte", "secret").unwrap();
+        let secret_store = CachedStore::new(memory_store);
+
+        secret_store.get("matcha_latte").unwrap();
+        secret_store.set("matcha_latte", "secret").unwrap();
+
+        assert_eq!(secret_store.inner().writes(), 1);
+    }
+}
@@ -0,0 +1,45 @@
+use tauri::{AppHandle, Manager};
+
+use super::payloads::{SecretStorageError, SetSecretStorageRequest};
+use super::store::SecretStorage;
+use crate::AppState;
+
+#[tauri::command]
+pub fn get_secret_storage(state: tauri::State<'_, AppState>) -> SecretStorage {
+    state.accounts_config.lock().unwrap().secret_storage()
+}
+
+/// Moves every account's secrets into or out of the platform secret store.
+/// Kept off the main thread, the store may wait for the user to unlock it.
+#[tauri::command]
+pub async fn set_secret_storage(
+    app: AppHandle,
+    payload: SetSecretStorageRequest,
+) -> Result<(), SecretStorageError> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut accounts_config = state.accounts_config.lock().unwrap();
+        accounts_config.set_secret_storage(payload.secret_storage)?;
+        accounts_config
+            .save_to_config(&state.config_path)
+            .map_err(|err| SecretStorageError::IOError(err.to_string()))?;
+        accounts_config.delete_stale_secrets();
+        let removed = match payload.secret_storage {
+            SecretStorage::Keyring => accounts_config.remove_plain_backups(&state.config_path),
+            SecretStorage::File => Ok(()),
+        };
+        if let Err(err) = removed {
+            log::error!("Could not remove the backups taken before the secret store: {err}");
+        }
+        log::info!("Moved the account secrets to {:?}", payload.secret_storage);
+        Ok(())
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            SecretStorageError::IOError(err.to_string())
+        })
+        .flatten()
+}
@@ -0,0 +1,54 @@
+use keyring::Entry;
+
+use super::store::{SecretStore, SecretStoreError};
+
+/// Secret Service on Linux, Keychain on macOS and Credential Manager on Windows.
+/// Each account is one entry of the app's service, named after the account.
+#[derive(Debug)]
+pub struct KeyringStore {
+    service: String,
+}
+
+impl KeyringStore {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, account_name: &str) -> Result<Entry, SecretStoreError> {
+        Entry::new(&self.service, account_name).map_err(into_secret_store_error)
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, account_name: &str) -> Result<Option<String>, SecretStoreError> {
+        match self.entry(account_name)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(into_secret_store_error(err)),
+        }
+    }
+
+    fn set(&self, account_name: &str, secret: &str) -> Result<(), SecretStoreError> {
+        self.entry(account_name)?
+            .set_password(secret)
+            .map_err(into_secret_store_error)
+    }
+
+    fn delete(&self, account_name: &str) -> Result<(), SecretStoreError> {
+        match self.entry(account_name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(into_secret_store_error(err)),
+        }
+    }
+}
+
+fn into_secret_store_error(err: keyring::Error) -> SecretStoreError {
+    match err {
+        keyring::Error::PlatformFailure(err) | keyring::Error::NoStorageAccess(err) => {
+            SecretStoreError::Unavailable(err.to_string())
+        }
+        err => SecretStoreError::Failed(err.to_string()),
+    }
+}
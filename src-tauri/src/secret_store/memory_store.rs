@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::store::{SecretStore, SecretStoreError};
+
+/// Stands in for the platform secret store in tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    secrets: Mutex<HashMap<String, String>>,
+    reads: Mutex<usize>,
+    writes: Mutex<usize>,
+    unavailable: bool,
+}
+
+impl MemoryStore {
+    /// Fails every call like a Linux desktop without a secret service
+    pub fn unavailable() -> Self {
+        Self {
+            unavailable: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn reads(&self) -> usize {
+        *self.reads.lock().unwrap()
+    }
+
+    pub fn writes(&self) -> usize {
+        *self.writes.lock().unwrap()
+    }
+
+    pub fn contains(&self, account_name: &str) -> bool {
+        self.secrets.lock().unwrap().contains_key(account_name)
+    }
+
+    fn check_available(&self) -> Result<(), SecretStoreError> {
+        if self.unavailable {
+            return Err(SecretStoreError::Unavailable(
+                "org.freedesktop.secrets was not provided by any .service files".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account_name: &str) -> Result<Option<String>, SecretStoreError> {
+        self.check_available()?;
+        *self.reads.lock().unwrap() += 1;
+        Ok(self.secrets.lock().unwrap().get(account_name).cloned())
+    }
+
+    fn set(&self, account_name: &str, secret: &str) -> Result<(), SecretStoreError> {
+        self.check_available()?;
+        *self.writes.lock().unwrap() += 1;
+        self.secrets
+            .lock()
+            .unwrap()
+            .insert(account_name.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account_name: &str) -> Result<(), SecretStoreError> {
+        self.check_available()?;
+        self.secrets.lock().unwrap().remove(account_name);
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod cached_store;
+pub mod commands;
+pub mod keyring_store;
+#[cfg(test)]
+pub mod memory_store;
+pub mod payloads;
+pub mod store;
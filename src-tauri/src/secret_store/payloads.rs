@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::store::{SecretStorage, SecretStoreError};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSecretStorageRequest {
+    pub secret_storage: SecretStorage,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum SecretStorageError {
+    Locked,
+    /// Remove the passkey first, the secret store replaces it
+    PasskeyEnabled,
+    Recovering,
+    /// Usually no Secret Service is running on Linux
+    Unavailable(String),
+    IOError(String),
+}
+
+impl From<SecretStoreError> for SecretStorageError {
+    fn from(value: SecretStoreError) -> Self {
+        match value {
+            SecretStoreError::Unavailable(err) => SecretStorageError::Unavailable(err),
+            err => SecretStorageError::IOError(err.to_string()),
+        }
+    }
+}
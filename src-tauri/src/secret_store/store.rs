@@ -0,0 +1,161 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::auth::user_credentials::UserCredentials;
+use crate::common::secret::Secret;
+
+/// Where account secrets are kept. Chosen per install and stored in the manifest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretStorage {
+    /// Inside the account files, sealed while a passkey is set
+    #[default]
+    File,
+    /// In the platform secret store, the account files only hold metadata
+    Keyring,
+}
+
+/// Holds one secret string per account. [`AccountsConfig`] only talks to this trait, so
+/// tests can swap the platform store for an in-memory one.
+///
+/// [`AccountsConfig`]: crate::account_manager::accounts_config::AccountsConfig
+pub trait SecretStore: fmt::Debug + Send + Sync {
+    fn get(&self, account_name: &str) -> Result<Option<String>, SecretStoreError>;
+    fn set(&self, account_name: &str, secret: &str) -> Result<(), SecretStoreError>;
+    /// Deleting a missing entry is not an error
+    fn delete(&self, account_name: &str) -> Result<(), SecretStoreError>;
+}
+
+#[derive(Debug)]
+pub enum SecretStoreError {
+    /// No secret service is running, or it refused access
+    Unavailable(String),
+    /// The account file says its secrets are in the store, but they are not
+    Missing,
+    Failed(String),
+}
+
+impl fmt::Display for SecretStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretStoreError::Unavailable(err) => {
+                write!(f, "the secret store is unavailable: {err}")
+            }
+            SecretStoreError::Missing => write!(f, "the secrets are missing from the secret store"),
+            SecretStoreError::Failed(err) => write!(f, "the secret store failed: {err}"),
+        }
+    }
+}
+
+/// The parts of an account that stay out of the account file with [`SecretStorage::Keyring`].
+/// `AccountSecrets::take(&mut account)` followed by a drop wipes an account's secrets.
+#[derive(Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountSecrets {
+    account_password: Secret,
+    shared_secret: Secret,
+    identity_secret: Secret,
+    revocation_code: Option<Secret>,
+    secret_1: Option<Secret>,
+    access_token: Secret,
+    refresh_token: Secret,
+    /// May contain `steamLoginSecure`, which is as good as the access token
+    cookies: Option<Secret>,
+}
+
+impl AccountSecrets {
+    /// Moves the secrets out, leaving the account with empty ones
+    pub fn take(account: &mut UserCredentials) -> Self {
+        Self {
+            account_password: std::mem::take(&mut account.account_password),
+            shared_secret: std::mem::take(&mut account.shared_secret),
+            identity_secret: std::mem::take(&mut account.identity_secret),
+            revocation_code: account.revocation_code.take(),
+            secret_1: account.secret_1.take(),
+            access_token: std::mem::take(&mut account.access_token),
+            refresh_token: std::mem::take(&mut account.refresh_token),
+            cookies: account.cookies.take(),
+        }
+    }
+
+    pub fn put_back(self, account: &mut UserCredentials) {
+        account.account_password = self.account_password;
+        account.shared_secret = self.shared_secret;
+        account.identity_secret = self.identity_secret;
+        account.revocation_code = self.revocation_code;
+        account.secret_1 = self.secret_1;
+        account.access_token = self.access_token;
+        account.refresh_token = self.refresh_token;
+        account.cookies = self.cookies;
+    }
+}
+
+/// Fills in the secrets of an account that was read without them
+pub fn load_secrets(
+    secret_store: &dyn SecretStore,
+    account: &mut UserCredentials,
+) -> Result<(), SecretStoreError> {
+    let secret = secret_store
+        .get(&account.account_name)?
+        .map(Zeroizing::new)
+        .ok_or(SecretStoreError::Missing)?;
+    let secrets = serde_json::from_str::<AccountSecrets>(&secret)
+        .map_err(|err| SecretStoreError::Failed(err.to_string()))?;
+    secrets.put_back(account);
+    Ok(())
+}
+
+/// Writes the secrets. Wrap the platform store in a [`CachedStore`] so unchanged
+/// secrets are not written again.
+///
+/// [`CachedStore`]: super::cached_store::CachedStore
+pub fn store_secrets(
+    secret_store: &dyn SecretStore,
+    account_name: &str,
+    secrets: &AccountSecrets,
+) -> Result<(), SecretStoreError> {
+    let secret = serde_json::to_string(secrets)
+        .map(Zeroizing::new)
+        .map_err(|err| SecretStoreError::Failed(err.to_string()))?;
+    secret_store.set(account_name, &secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_store::memory_store::MemoryStore;
+
+    #[test]
+    fn moves_secrets_through_the_store() {
+        let secret_store = MemoryStore::default();
+        let mut account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            shared_secret: "FSY2y2mThnpJv1h+lXKTVuH+cvQ=".into(),
+            refresh_token: "refresh".into(),
+            ..Default::default()
+        };
+
+        let secrets = AccountSecrets::take(&mut account);
+        store_secrets(&secret_store, &account.account_name, &secrets).unwrap();
+        let stripped = account.clone();
+        load_secrets(&secret_store, &mut account).unwrap();
+
+        assert!(stripped.shared_secret.is_empty() && stripped.refresh_token.is_empty());
+        assert_eq!(
+            account.shared_secret.expose(),
+            "FSY2y2mThnpJv1h+lXKTVuH+cvQ="
+        );
+        assert_eq!(account.refresh_token.expose(), "refresh");
+    }
+
+    #[test]
+    fn reports_missing_secrets() {
+        let mut account = UserCredentials {
+            account_name: "matcha_latte".to_string(),
+            ..Default::default()
+        };
+        let result = load_secrets(&MemoryStore::default(), &mut account);
+        assert!(matches!(result, Err(SecretStoreError::Missing)));
+    }
+}
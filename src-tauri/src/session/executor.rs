@@ -0,0 +1,190 @@
+use steamguard::SteamGuardAccount;
+use tauri::{AppHandle, Manager};
+
+use super::payloads::SteamError;
+use crate::AppState;
+use crate::auth::relogin::relogin_stored_account;
+use crate::auth::user_credentials::{TokenRefreshError, UserCredentials};
+
+/// Runs `operation` for the active account on a blocking thread. Tokens close to expiry are
+/// refreshed first, and an auth failure is retried once after renewing the session.
+pub async fn with_active_account<T, F>(app: AppHandle, operation: F) -> Result<T, SteamError>
+where
+    T: Send + 'static,
+    F: Fn(&AppState, &SteamGuardAccount) -> Result<T, SteamError> + Send + 'static,
+{
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        run_with_active_account(&app.state::<AppState>(), operation)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            SteamError::ApiError
+        })
+        .flatten()
+}
+
+fn run_with_active_account<T>(
+    state: &AppState,
+    operation: impl Fn(&AppState, &SteamGuardAccount) -> Result<T, SteamError>,
+) -> Result<T, SteamError> {
+    let account = active_account(state)?;
+    if let Err(err) = renew_session(state, account, false) {
+        // Steam gets the final say, the operation may still go through
+        log::warn!("Could not refresh the session before calling Steam: {err:?}");
+    }
+
+    retry_after_renewal(
+        || operation(state, &active_steam_guard_account(state)?),
+        || renew_session(state, active_account(state)?, true),
+    )
+}
+
+/// Runs `operation`, and once more after `renew` if Steam refused the session. Any other
+/// error, or a second refusal, is returned as it is.
+fn retry_after_renewal<T>(
+    operation: impl Fn() -> Result<T, SteamError>,
+    renew: impl FnOnce() -> Result<(), SteamError>,
+) -> Result<T, SteamError> {
+    match operation() {
+        Err(SteamError::Unauthorized) => {
+            log::info!("Steam refused the session, renewing it and trying again");
+            renew()?;
+            operation()
+        }
+        result => result,
+    }
+}
+
+fn active_account(state: &AppState) -> Result<UserCredentials, SteamError> {
+    let accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(SteamError::Locked);
+    }
+    accounts_config
+        .get_active_account()
+        .cloned()
+        .ok_or(SteamError::Unauthorized)
+}
+
+fn active_steam_guard_account(state: &AppState) -> Result<SteamGuardAccount, SteamError> {
+    state
+        .get_active_steam_guard_account()
+        .ok_or(SteamError::Unauthorized)
+}
+
+/// Refreshes the tokens, falling back to a new login for accounts that opted in
+fn renew_session(
+    state: &AppState,
+    mut account: UserCredentials,
+    force: bool,
+) -> Result<(), SteamError> {
+    let refreshed = if force {
+        account
+            .refresh_tokens(state.transport.clone())
+            .map(|()| true)
+    } else {
+        account.refresh_tokens_if_needed(state.transport.clone())
+    };
+    match refreshed {
+        Ok(false) => Ok(()),
+        Ok(true) => {
+            let mut accounts_config = state.accounts_config.lock().unwrap();
+            accounts_config.store_tokens(&account);
+            accounts_config
+                .save_to_config(&state.config_path)
+                .map_err(|err| {
+                    log::error!("Failed to save refreshed tokens: {err}");
+                    SteamError::ApiError
+                })
+        }
+        Err(TokenRefreshError::Network(err)) => {
+            log::warn!("Could not refresh tokens: {err}");
+            Err(SteamError::NetworkFailure)
+        }
+        Err(err) if account.auto_relogin => {
+            log::info!("{err}, logging in to {} again", account.account_name);
+            relogin_stored_account(state, &account.account_name).map_err(|relogin_err| {
+                log::warn!("Could not log in again: {relogin_err:?}");
+                SteamError::Unauthorized
+            })
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn renews_once_and_retries_after_unauthorized() {
+        let calls = Cell::new(0);
+        let renewals = Cell::new(0);
+        let result = retry_after_renewal(
+            || {
+                calls.set(calls.get() + 1);
+                match calls.get() {
+                    1 => Err(SteamError::Unauthorized),
+                    _ => Ok("confirmed"),
+                }
+            },
+            || {
+                renewals.set(renewals.get() + 1);
+                Ok(())
+            },
+        );
+        assert_eq!(result, Ok("confirmed"));
+        assert_eq!((calls.get(), renewals.get()), (2, 1));
+    }
+
+    #[test]
+    fn gives_up_after_a_second_unauthorized() {
+        let calls = Cell::new(0);
+        let renewals = Cell::new(0);
+        let result: Result<(), _> = retry_after_renewal(
+            || {
+                calls.set(calls.get() + 1);
+                Err(SteamError::Unauthorized)
+            },
+            || {
+                renewals.set(renewals.get() + 1);
+                Ok(())
+            },
+        );
+        assert_eq!(result, Err(SteamError::Unauthorized));
+        assert_eq!((calls.get(), renewals.get()), (2, 1));
+    }
+
+    #[test]
+    fn does_not_retry_other_errors() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = retry_after_renewal(
+            || {
+                calls.set(calls.get() + 1);
+                Err(SteamError::NetworkFailure)
+            },
+            || panic!("Only an unauthorized session gets renewed"),
+        );
+        assert_eq!(result, Err(SteamError::NetworkFailure));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn returns_the_renewal_error_without_retrying() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = retry_after_renewal(
+            || {
+                calls.set(calls.get() + 1);
+                Err(SteamError::Unauthorized)
+            },
+            || Err(SteamError::NetworkFailure),
+        );
+        assert_eq!(result, Err(SteamError::NetworkFailure));
+        assert_eq!(calls.get(), 1);
+    }
+}
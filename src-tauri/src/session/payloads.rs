@@ -0,0 +1,51 @@
+use serde::Serialize;
+use steamguard::{ApproverError, ConfirmerError};
+
+use crate::auth::user_credentials::TokenRefreshError;
+
+/// Shared by every command that talks to Steam on behalf of the active account
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SteamError {
+    /// No active account, or its session could not be renewed
+    Unauthorized,
+    Locked,
+    Expired,
+    DuplicateRequest,
+    DeserializationError,
+    NetworkFailure,
+    ApiError,
+}
+
+impl From<ConfirmerError> for SteamError {
+    fn from(err: ConfirmerError) -> Self {
+        log::debug!("Received confirmation error: {err:?}");
+        match err {
+            ConfirmerError::InvalidTokens => SteamError::Unauthorized,
+            ConfirmerError::DeserializeError(_) => SteamError::DeserializationError,
+            ConfirmerError::NetworkFailure(_) => SteamError::NetworkFailure,
+            _ => SteamError::ApiError,
+        }
+    }
+}
+
+impl From<ApproverError> for SteamError {
+    fn from(err: ApproverError) -> Self {
+        log::debug!("Received authentication request approval error: {err:?}");
+        match err {
+            ApproverError::DuplicateRequest => SteamError::DuplicateRequest,
+            ApproverError::Expired => SteamError::Expired,
+            ApproverError::Unauthorized => SteamError::Unauthorized,
+            _ => SteamError::ApiError,
+        }
+    }
+}
+
+impl From<TokenRefreshError> for SteamError {
+    fn from(err: TokenRefreshError) -> Self {
+        match err {
+            TokenRefreshError::Network(_) => SteamError::NetworkFailure,
+            _ => SteamError::Unauthorized,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use steamguard::protobufs::steammessages_twofactor_steamclient::{
+    CTwoFactor_Time_Request, CTwoFactor_Time_Response,
+};
+use steamguard::steamapi::ApiRequest;
+use steamguard::transport::{Transport, TransportError};
+
+use crate::auth::user_credentials::unix_now;
+
+/// Local clock corrected by the offset to Steam's clock. Codes generated from
+/// a drifted clock get rejected, so every code generation path goes through this.
+#[derive(Debug, Default)]
+pub struct SteamClock {
+    offset_secs: AtomicI64,
+}
+
+impl SteamClock {
+    /// Steam's current unix time, as far as we know
+    pub fn now(&self) -> u64 {
+        apply_offset(unix_now(), self.offset())
+    }
+
+    /// Seconds to add to the local clock to get Steam's time
+    pub fn offset(&self) -> i64 {
+        self.offset_secs.load(Ordering::Relaxed)
+    }
+
+    /// Queries `ITwoFactorService/QueryTime` and stores the new offset
+    pub fn sync(&self, transport: impl Transport) -> Result<i64, TransportError> {
+        let requested_at = unix_now();
+        let request = ApiRequest::new(
+            "ITwoFactorService",
+            "QueryTime",
+            1,
+            CTwoFactor_Time_Request::new(),
+        );
+        let response = transport
+            .send_request::<CTwoFactor_Time_Request, CTwoFactor_Time_Response>(request)?
+            .into_response_data();
+        let local_time = round_trip_midpoint(requested_at, unix_now());
+        let offset = offset_between(response.server_time(), local_time);
+        self.offset_secs.store(offset, Ordering::Relaxed);
+        Ok(offset)
+    }
+}
+
+/// Halfway through the round trip is the best guess for when Steam read its clock. The local
+/// clock may have been set back in the meantime, that counts as an instant response.
+fn round_trip_midpoint(requested_at: u64, received_at: u64) -> u64 {
+    requested_at + received_at.saturating_sub(requested_at) / 2
+}
+
+fn offset_between(server_time: u64, local_time: u64) -> i64 {
+    server_time as i64 - local_time as i64
+}
+
+fn apply_offset(local_time: u64, offset: i64) -> u64 {
+    local_time.saturating_add_signed(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_offset_for_clock_behind_steam() {
+        let offset = offset_between(1_700_000_030, 1_700_000_000);
+        assert_eq!(offset, 30);
+        assert_eq!(apply_offset(1_700_000_000, offset), 1_700_000_030);
+    }
+
+    #[test]
+    fn computes_offset_for_clock_ahead_of_steam() {
+        let offset = offset_between(1_700_000_000, 1_700_000_045);
+        assert_eq!(offset, -45);
+        assert_eq!(apply_offset(1_700_000_045, offset), 1_700_000_000);
+    }
+
+    #[test]
+    fn survives_the_clock_going_back_during_a_sync() {
+        assert_eq!(
+            round_trip_midpoint(1_700_000_000, 1_700_000_004),
+            1_700_000_002
+        );
+        assert_eq!(
+            round_trip_midpoint(1_700_000_000, 1_699_999_000),
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn starts_without_offset() {
+        let clock = SteamClock::default();
+        assert_eq!(clock.offset(), 0);
+    }
+}
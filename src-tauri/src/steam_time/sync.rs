@@ -0,0 +1,32 @@
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::AppState;
+
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keeps the offset to Steam's clock up to date on a background thread
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("steam-time-sync".to_string())
+        .spawn(move || {
+            loop {
+                let state = app.state::<AppState>();
+                let next_sync = match state.steam_clock.sync(state.transport.clone()) {
+                    Ok(offset) => {
+                        log::debug!("Synced with Steam's clock, offset is {offset}s");
+                        RESYNC_INTERVAL
+                    }
+                    Err(err) => {
+                        log::warn!("Could not query Steam's time: {err}");
+                        RETRY_INTERVAL
+                    }
+                };
+                thread::sleep(next_sync);
+            }
+        })
+        .expect("Could not spawn the time sync thread");
+}
@@ -0,0 +1,2 @@
+pub mod payloads;
+pub mod scheduler;
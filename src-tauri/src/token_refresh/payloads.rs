@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+/// Emitted once per dead refresh token, the account needs a new login
+pub const REFRESH_TOKEN_EXPIRED_EVENT: &str = "refresh-token-expired";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenExpiredEvent {
+    pub account_name: String,
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::payloads::{REFRESH_TOKEN_EXPIRED_EVENT, RefreshTokenExpiredEvent};
+use crate::AppState;
+use crate::auth::relogin::relogin_stored_account;
+use crate::auth::user_credentials::{
+    REFRESH_MARGIN_SECS, TokenRefreshError, UserCredentials, unix_now,
+};
+use crate::common::secret::Secret;
+
+const MIN_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Also bounds how long a newly added account or an unlocked vault waits for its first check
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Keeps every account's access token fresh on a background thread
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("token-refresh".to_string())
+        .spawn(move || {
+            let mut scheduler = TokenRefreshScheduler::default();
+            loop {
+                let next_check = scheduler.run_once(&app);
+                log::debug!("Next token refresh check in {}s", next_check.as_secs());
+                thread::sleep(next_check);
+            }
+        })
+        .expect("Could not spawn the token refresh thread");
+}
+
+#[derive(Default)]
+struct TokenRefreshScheduler {
+    /// Account name to the refresh token Steam rejected. A new login replaces the token
+    /// and takes the account out of here.
+    dead_tokens: HashMap<String, Secret>,
+}
+
+impl TokenRefreshScheduler {
+    /// Refreshes the accounts that are due and returns how long to sleep
+    fn run_once(&mut self, app: &AppHandle) -> Duration {
+        let state = app.state::<AppState>();
+        let accounts = {
+            let accounts_config = state.accounts_config.lock().unwrap();
+            if accounts_config.is_locked() {
+                return MAX_CHECK_INTERVAL;
+            }
+            accounts_config
+                .accounts
+                .iter()
+                .filter(|account| !account.access_token.is_empty())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        self.dead_tokens.retain(|account_name, refresh_token| {
+            accounts.iter().any(|account| {
+                &account.account_name == account_name && &account.refresh_token == refresh_token
+            })
+        });
+
+        let now = unix_now();
+        let mut expirations = Vec::new();
+        let mut had_failure = false;
+        for mut account in accounts {
+            if self.dead_tokens.contains_key(&account.account_name) {
+                continue;
+            }
+            match account.refresh_tokens_if_needed(state.transport.clone()) {
+                Ok(refreshed) => {
+                    if refreshed {
+                        store_tokens(&state, &account);
+                    }
+                    expirations.extend(account.access_token_expires_at().ok());
+                }
+                Err(TokenRefreshError::Network(err)) => {
+                    log::warn!(
+                        "Could not refresh tokens of {}: {err}",
+                        account.account_name
+                    );
+                    had_failure = true;
+                }
+                Err(err) => {
+                    if account.auto_relogin {
+                        match relogin_stored_account(&state, &account.account_name) {
+                            Ok(()) => continue,
+                            Err(relogin_err) => log::warn!(
+                                "Could not log in to {} again: {relogin_err:?}",
+                                account.account_name
+                            ),
+                        }
+                    }
+                    log::warn!("{} needs a new login, {err}", account.account_name);
+                    self.dead_tokens
+                        .insert(account.account_name.clone(), account.refresh_token);
+                    let event = RefreshTokenExpiredEvent {
+                        account_name: account.account_name,
+                    };
+                    if let Err(err) = app.emit(REFRESH_TOKEN_EXPIRED_EVENT, event) {
+                        log::error!("Could not emit {REFRESH_TOKEN_EXPIRED_EVENT}: {err}");
+                    }
+                }
+            }
+        }
+
+        if had_failure {
+            return MIN_CHECK_INTERVAL;
+        }
+        next_check_in(&expirations, now)
+    }
+}
+
+fn store_tokens(state: &AppState, refreshed: &UserCredentials) {
+    let mut accounts_config = state.accounts_config.lock().unwrap();
+    if !accounts_config.store_tokens(refreshed) {
+        return;
+    }
+    if let Err(err) = accounts_config.save_to_config(&state.config_path) {
+        log::error!("Failed to save refreshed tokens: {err}");
+    }
+}
+
+fn next_check_in(expirations: &[u64], now: u64) -> Duration {
+    let refresh_from = now + REFRESH_MARGIN_SECS;
+    expirations
+        .iter()
+        .map(|expires_at| Duration::from_secs(expires_at.saturating_sub(refresh_from)))
+        .min()
+        .unwrap_or(MAX_CHECK_INTERVAL)
+        .clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wakes_up_before_the_earliest_expiry() {
+        let now = 1_000_000;
+        let expirations = [now + REFRESH_MARGIN_SECS + 120, now + 3600];
+        assert_eq!(next_check_in(&expirations, now), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn clamps_check_interval() {
+        let now = 1_000_000;
+        assert_eq!(next_check_in(&[now], now), MIN_CHECK_INTERVAL);
+        assert_eq!(next_check_in(&[now + 86_400], now), MAX_CHECK_INTERVAL);
+        assert_eq!(next_check_in(&[], now), MAX_CHECK_INTERVAL);
+    }
+}
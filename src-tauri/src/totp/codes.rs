@@ -0,0 +1,53 @@
+use steamguard::token::TwoFactorSecret;
+
+/// Steam Guard codes rotate every 30 seconds
+pub const CODE_PERIOD_SECS: u64 = 30;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CodeWindow {
+    pub code: String,
+    pub next_code: String,
+    pub seconds_remaining: u64,
+}
+
+/// `shared_secret` is base64 as stored in the config
+pub fn code_window(shared_secret: &str, steam_time: u64) -> Option<CodeWindow> {
+    let secret = TwoFactorSecret::parse_shared_secret(shared_secret.to_string()).ok()?;
+    Some(CodeWindow {
+        code: secret.generate_code(steam_time),
+        next_code: secret.generate_code(steam_time + CODE_PERIOD_SECS),
+        seconds_remaining: seconds_remaining(steam_time),
+    })
+}
+
+pub fn seconds_remaining(steam_time: u64) -> u64 {
+    CODE_PERIOD_SECS - steam_time % CODE_PERIOD_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARED_SECRET: &str = "FSY2y2mThnpJv1h+lXKTVuH+cvQ=";
+    /// Start of a period
+    const PERIOD_START: u64 = 1_699_999_980;
+
+    #[test]
+    fn counts_down_to_the_period_boundary() {
+        assert_eq!(seconds_remaining(PERIOD_START), 30);
+        assert_eq!(seconds_remaining(PERIOD_START + 10), 20);
+        assert_eq!(seconds_remaining(PERIOD_START + 29), 1);
+    }
+
+    #[test]
+    fn next_code_is_the_code_of_the_following_period() {
+        let current = code_window(SHARED_SECRET, PERIOD_START + 10).unwrap();
+        let following = code_window(SHARED_SECRET, PERIOD_START + 30).unwrap();
+        assert_eq!(current.next_code, following.code);
+    }
+
+    #[test]
+    fn rejects_invalid_secret() {
+        assert_eq!(code_window("not a secret", PERIOD_START), None);
+    }
+}
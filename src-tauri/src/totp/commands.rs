@@ -0,0 +1,100 @@
+use validator::Validate;
+
+use super::codes::{CODE_PERIOD_SECS, code_window, seconds_remaining};
+use super::payloads::{
+    AccountCodeResponse, CodeError, CodeResponse, CodesFilter, CodesResponse, GetCodeRequest,
+};
+use crate::AppState;
+
+#[tauri::command]
+pub fn get_code(
+    state: tauri::State<'_, AppState>,
+    payload: GetCodeRequest,
+) -> Result<CodeResponse, CodeError> {
+    payload.validate()?;
+    let accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(CodeError::Locked);
+    }
+    let account = accounts_config
+        .accounts
+        .iter()
+        .find(|account| account.account_name == payload.account_name)
+        .ok_or(CodeError::AccountNotFound)?;
+    if !account.has_authenticator() {
+        return Err(CodeError::NoAuthenticator);
+    }
+
+    let window = code_window(account.shared_secret.expose(), state.steam_clock.now())
+        .ok_or(CodeError::InvalidSecret)?;
+    Ok(CodeResponse {
+        account_name: account.account_name.clone(),
+        code: window.code,
+        seconds_remaining: window.seconds_remaining,
+        next_code: window.next_code,
+        period: CODE_PERIOD_SECS,
+        time_offset: state.steam_clock.offset(),
+    })
+}
+
+#[tauri::command]
+pub fn get_codes(
+    state: tauri::State<'_, AppState>,
+    payload: CodesFilter,
+) -> Result<CodesResponse, CodeError> {
+    codes_response(&state, &payload)
+}
+
+/// Starts emitting [`super::payloads::CODES_EVENT`] with the matching codes at every period
+/// boundary, replacing the previous filter. Returns the current codes right away.
+#[tauri::command]
+pub fn watch_codes(
+    state: tauri::State<'_, AppState>,
+    payload: CodesFilter,
+) -> Result<CodesResponse, CodeError> {
+    let codes = codes_response(&state, &payload)?;
+    *state.codes_filter.lock().unwrap() = Some(payload);
+    Ok(codes)
+}
+
+#[tauri::command]
+pub fn unwatch_codes(state: tauri::State<'_, AppState>) {
+    *state.codes_filter.lock().unwrap() = None;
+}
+
+/// Accounts without an authenticator are left out
+pub(super) fn codes_response(
+    state: &AppState,
+    filter: &CodesFilter,
+) -> Result<CodesResponse, CodeError> {
+    let steam_time = state.steam_clock.now();
+    let accounts_config = state.accounts_config.lock().unwrap();
+    if accounts_config.is_locked() {
+        return Err(CodeError::Locked);
+    }
+    let codes = accounts_config
+        .accounts
+        .iter()
+        .filter(|account| account.has_authenticator() && filter.matches(account))
+        .filter_map(|account| {
+            let Some(window) = code_window(account.shared_secret.expose(), steam_time) else {
+                log::warn!(
+                    "Skipping {}, its shared secret is invalid",
+                    account.account_name
+                );
+                return None;
+            };
+            Some(AccountCodeResponse {
+                account_name: account.account_name.clone(),
+                code: window.code,
+                next_code: window.next_code,
+            })
+        })
+        .collect();
+    Ok(CodesResponse {
+        codes,
+        seconds_remaining: seconds_remaining(steam_time),
+        period: CODE_PERIOD_SECS,
+        time_offset: state.steam_clock.offset(),
+    })
+}
@@ -0,0 +1,4 @@
+pub mod codes;
+pub mod commands;
+pub mod payloads;
+pub mod stream;
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use crate::auth::user_credentials::UserCredentials;
+
+/// Emitted at every period boundary while the frontend watches the codes
+pub const CODES_EVENT: &str = "codes";
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCodeRequest {
+    #[validate(length(min = 1))]
+    pub account_name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeResponse {
+    pub account_name: String,
+    pub code: String,
+    /// Until `code` stops being valid
+    pub seconds_remaining: u64,
+    pub next_code: String,
+    pub period: u64,
+    /// Seconds added to the local clock to match Steam's
+    pub time_offset: i64,
+}
+
+/// Both conditions have to match. Leaving one out matches every account.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodesFilter {
+    /// Case insensitive substring of the account name
+    pub name: Option<String>,
+    /// Case insensitive tag
+    pub tag: Option<String>,
+}
+
+impl CodesFilter {
+    pub fn matches(&self, account: &UserCredentials) -> bool {
+        let name_matches = self.name.as_ref().is_none_or(|name| {
+            account
+                .account_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        });
+        let tag_matches = self.tag.as_ref().is_none_or(|tag| {
+            account
+                .tags
+                .iter()
+                .any(|account_tag| account_tag.eq_ignore_ascii_case(tag))
+        });
+        name_matches && tag_matches
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountCodeResponse {
+    pub account_name: String,
+    pub code: String,
+    pub next_code: String,
+}
+
+/// All codes share the same period, so the timing is only sent once
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodesResponse {
+    pub codes: Vec<AccountCodeResponse>,
+    pub seconds_remaining: u64,
+    pub period: u64,
+    pub time_offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum CodeError {
+    AccountNotFound,
+    NoAuthenticator,
+    InvalidSecret,
+    Locked,
+    ValidationError(String),
+}
+
+impl From<ValidationErrors> for CodeError {
+    fn from(value: ValidationErrors) -> Self {
+        CodeError::ValidationError(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(account_name: &str, tags: &[&str]) -> UserCredentials {
+        UserCredentials {
+            account_name: account_name.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(CodesFilter::default().matches(&account("matcha_latte", &[])));
+    }
+
+    #[test]
+    fn matches_name_substring_ignoring_case() {
+        let filter = CodesFilter {
+            name: Some("LATTE".to_string()),
+            tag: None,
+        };
+        assert!(filter.matches(&account("matcha_latte", &[])));
+        assert!(!filter.matches(&account("espresso", &[])));
+    }
+
+    #[test]
+    fn requires_both_name_and_tag() {
+        let filter = CodesFilter {
+            name: Some("matcha".to_string()),
+            tag: Some("trading".to_string()),
+        };
+        assert!(filter.matches(&account("matcha_latte", &["Trading"])));
+        assert!(!filter.matches(&account("matcha_latte", &["market"])));
+        assert!(!filter.matches(&account("espresso", &["trading"])));
+    }
+}
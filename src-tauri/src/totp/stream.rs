@@ -0,0 +1,37 @@
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::codes::seconds_remaining;
+use super::commands::codes_response;
+use super::payloads::CODES_EVENT;
+use crate::AppState;
+
+/// Emits the codes of the watched accounts whenever they rotate
+pub fn spawn(app: AppHandle) {
+    thread::Builder::new()
+        .name("codes-stream".to_string())
+        .spawn(move || {
+            loop {
+                let state = app.state::<AppState>();
+                thread::sleep(Duration::from_secs(seconds_remaining(
+                    state.steam_clock.now(),
+                )));
+
+                let Some(filter) = state.codes_filter.lock().unwrap().clone() else {
+                    continue;
+                };
+                match codes_response(&state, &filter) {
+                    Ok(codes) => {
+                        if let Err(err) = app.emit(CODES_EVENT, codes) {
+                            log::error!("Could not emit the codes: {err}");
+                        }
+                    }
+                    // Nothing to show until the vault is unlocked
+                    Err(err) => log::debug!("Not emitting codes: {err:?}"),
+                }
+            }
+        })
+        .expect("Could not spawn the codes stream thread");
+}
@@ -0,0 +1,121 @@
+use tauri::{AppHandle, Manager};
+use validator::Validate;
+
+use super::payloads::{
+    ChangePasskeyRequest, EnablePasskeyRequest, RemovePasskeyRequest, UnlockVaultRequest,
+    VaultError, VaultStatusResponse,
+};
+use crate::AppState;
+use crate::account_manager::accounts_config::AccountsConfig;
+use crate::auto_lock::locking::lock_secrets;
+use crate::auto_lock::payloads::LockReason;
+
+#[tauri::command]
+pub fn get_vault_status(state: tauri::State<'_, AppState>) -> VaultStatusResponse {
+    let accounts_config = state.accounts_config.lock().unwrap();
+    VaultStatusResponse {
+        encrypted: accounts_config.is_encrypted(),
+        locked: accounts_config.is_locked(),
+    }
+}
+
+#[tauri::command]
+pub async fn unlock_vault(app: AppHandle, payload: UnlockVaultRequest) -> Result<(), VaultError> {
+    with_accounts_config(app, move |accounts_config, state| {
+        accounts_config.unlock(payload.passkey.expose())?;
+        if let Some(account) = accounts_config.get_active_account_mut() {
+            match account.refresh_tokens_if_needed(state.transport.clone()) {
+                Ok(true) => save(accounts_config, state)?,
+                Ok(false) => {}
+                Err(err) => log::warn!("Could not refresh tokens after unlocking: {err}"),
+            }
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn enable_passkey(
+    app: AppHandle,
+    payload: EnablePasskeyRequest,
+) -> Result<(), VaultError> {
+    payload.validate()?;
+    with_accounts_config(app, move |accounts_config, state| {
+        if accounts_config.is_encrypted() {
+            return Err(VaultError::AlreadyEncrypted);
+        }
+        accounts_config.set_passkey(Some(payload.passkey.expose()))?;
+        save(accounts_config, state)?;
+        if let Err(err) = accounts_config.remove_plain_backups(&state.config_path) {
+            log::error!("Could not remove the backups taken before the passkey: {err}");
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn change_passkey(
+    app: AppHandle,
+    payload: ChangePasskeyRequest,
+) -> Result<(), VaultError> {
+    payload.validate()?;
+    with_accounts_config(app, move |accounts_config, state| {
+        accounts_config.verify_passkey(payload.current_passkey.expose())?;
+        accounts_config.set_passkey(Some(payload.new_passkey.expose()))?;
+        save(accounts_config, state)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_passkey(
+    app: AppHandle,
+    payload: RemovePasskeyRequest,
+) -> Result<(), VaultError> {
+    with_accounts_config(app, move |accounts_config, state| {
+        accounts_config.verify_passkey(payload.current_passkey.expose())?;
+        accounts_config.set_passkey(None)?;
+        save(accounts_config, state)
+    })
+    .await
+}
+
+/// Locks right away instead of waiting for the auto-lock
+#[tauri::command]
+pub fn lock_vault(app: AppHandle) -> Result<(), VaultError> {
+    let state = app.state::<AppState>();
+    if !state.accounts_config.lock().unwrap().is_encrypted() {
+        return Err(VaultError::NotEncrypted);
+    }
+    lock_secrets(&app, LockReason::Manual);
+    Ok(())
+}
+
+fn save(accounts_config: &AccountsConfig, state: &AppState) -> Result<(), VaultError> {
+    accounts_config
+        .save_to_config(&state.config_path)
+        .map_err(|err| VaultError::IOError(err.to_string()))
+}
+
+/// Key derivation is deliberately expensive, so it is kept off the main thread
+async fn with_accounts_config<T, F>(app: AppHandle, f: F) -> Result<T, VaultError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut AccountsConfig, &AppState) -> Result<T, VaultError> + Send + 'static,
+{
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut accounts_config = state.accounts_config.lock().unwrap();
+        f(&mut accounts_config, &state)
+    })
+    .await;
+
+    result
+        .map_err(|err| {
+            log::error!("The spawned blocking task panicked or failed to join. {err}");
+            VaultError::IOError(err.to_string())
+        })
+        .flatten()
+}
@@ -0,0 +1,188 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+/// 1 GiB. Parameters are read from files, so anything above is treated as crafted.
+const MAX_MEMORY_COST: u32 = 1024 * 1024;
+const MAX_TIME_COST: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id parameters. They are stored next to the encrypted payload,
+/// so the defaults can be raised later without breaking existing vaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub salt: String,
+    /// In KiB
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// 64 MiB, 3 passes. Around half a second on a modern desktop.
+    pub fn generate() -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            salt: STANDARD.encode(salt),
+            memory_cost: 64 * 1024,
+            time_cost: 3,
+            parallelism: 1,
+        }
+    }
+
+    /// Whether deriving a key stays within a sane amount of memory and time
+    pub fn is_within_limits(&self) -> bool {
+        self.memory_cost <= MAX_MEMORY_COST
+            && self.time_cost <= MAX_TIME_COST
+            && self.parallelism <= MAX_PARALLELISM
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VaultCryptoError {
+    InvalidParams,
+    /// Either the passkey is wrong or the payload was tampered with
+    DecryptionFailed,
+    MalformedPayload,
+}
+
+/// Key derived from the user's passkey. The key bytes are wiped on drop.
+#[derive(Clone)]
+pub struct VaultKey {
+    params: KdfParams,
+    key: Zeroizing<[u8; KEY_LENGTH]>,
+}
+
+impl std::fmt::Debug for VaultKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultKey")
+            .field("params", &self.params)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl VaultKey {
+    pub fn derive(passkey: &str, params: KdfParams) -> Result<Self, VaultCryptoError> {
+        let salt = STANDARD
+            .decode(&params.salt)
+            .map_err(|_| VaultCryptoError::InvalidParams)?;
+        let argon2_params = Params::new(
+            params.memory_cost,
+            params.time_cost,
+            params.parallelism,
+            Some(KEY_LENGTH),
+        )
+        .map_err(|_| VaultCryptoError::InvalidParams)?;
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+            .hash_password_into(passkey.as_bytes(), &salt, key.as_mut())
+            .map_err(|_| VaultCryptoError::InvalidParams)?;
+
+        Ok(Self { params, key })
+    }
+
+    pub fn params(&self) -> &KdfParams {
+        &self.params
+    }
+
+    /// Checks whether `passkey` derives to this exact key
+    pub fn matches(&self, passkey: &str) -> bool {
+        Self::derive(passkey, self.params.clone())
+            .map(|other| other.key == self.key)
+            .unwrap_or(false)
+    }
+
+    /// Encrypts with a random nonce. Output is base64 of `nonce || ciphertext`.
+    pub fn seal(&self, plaintext: &[u8]) -> String {
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .expect("XChaCha20Poly1305 encryption does not fail for in-memory buffers");
+
+        let mut payload = Vec::with_capacity(NONCE_LENGTH + ciphertext.len());
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        STANDARD.encode(payload)
+    }
+
+    pub fn open(&self, sealed: &str) -> Result<Zeroizing<Vec<u8>>, VaultCryptoError> {
+        let payload = STANDARD
+            .decode(sealed)
+            .map_err(|_| VaultCryptoError::MalformedPayload)?;
+        if payload.len() < NONCE_LENGTH {
+            return Err(VaultCryptoError::MalformedPayload);
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map(Zeroizing::new)
+            .map_err(|_| VaultCryptoError::DecryptionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests don't spend seconds in Argon2
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_cost: 64,
+            time_cost: 1,
+            ..KdfParams::generate()
+        }
+    }
+
+    #[test]
+    fn opens_sealed_payload_with_same_passkey() {
+        let params = test_params();
+        let key = VaultKey::derive("correct horse battery staple", params.clone()).unwrap();
+        let sealed = key.seal(b"shared_secret");
+
+        let key = VaultKey::derive("correct horse battery staple", params).unwrap();
+        assert_eq!(key.open(&sealed).unwrap().as_slice(), b"shared_secret");
+    }
+
+    #[test]
+    fn fails_to_open_with_wrong_passkey() {
+        let params = test_params();
+        let sealed = VaultKey::derive("correct horse battery staple", params.clone())
+            .unwrap()
+            .seal(b"shared_secret");
+
+        let key = VaultKey::derive("Tr0ub4dor&3", params).unwrap();
+        assert_eq!(
+            key.open(&sealed).unwrap_err(),
+            VaultCryptoError::DecryptionFailed
+        );
+    }
+
+    #[test]
+    fn fails_to_open_truncated_payload() {
+        let key = VaultKey::derive("passkey", test_params()).unwrap();
+        assert_eq!(
+            key.open("AAAA").unwrap_err(),
+            VaultCryptoError::MalformedPayload
+        );
+    }
+
+    #[test]
+    fn matches_only_the_original_passkey() {
+        let key = VaultKey::derive("passkey", test_params()).unwrap();
+        assert!(key.matches("passkey"));
+        assert!(!key.matches("passkey2"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod commands;
+pub mod crypto;
+pub mod payloads;
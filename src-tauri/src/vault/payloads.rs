@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use super::crypto::VaultCryptoError;
+use crate::common::secret::Secret;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatusResponse {
+    pub encrypted: bool,
+    pub locked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockVaultRequest {
+    pub passkey: Secret,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnablePasskeyRequest {
+    #[validate(length(min = 8))]
+    pub passkey: Secret,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasskeyRequest {
+    pub current_passkey: Secret,
+
+    #[validate(length(min = 8))]
+    pub new_passkey: Secret,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovePasskeyRequest {
+    pub current_passkey: Secret,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "message")]
+pub enum VaultError {
+    Locked,
+    NotEncrypted,
+    AlreadyEncrypted,
+    WrongPasskey,
+    Corrupted,
+    /// Secrets are kept in the platform secret store, which rules out a passkey
+    SecretStoreInUse,
+    ValidationError(String),
+    IOError(String),
+}
+
+impl From<ValidationErrors> for VaultError {
+    fn from(value: ValidationErrors) -> Self {
+        VaultError::ValidationError(value.to_string())
+    }
+}
+
+impl From<VaultCryptoError> for VaultError {
+    fn from(value: VaultCryptoError) -> Self {
+        match value {
+            VaultCryptoError::DecryptionFailed => VaultError::WrongPasskey,
+            VaultCryptoError::InvalidParams | VaultCryptoError::MalformedPayload => {
+                VaultError::Corrupted
+            }
+        }
+    }
+}
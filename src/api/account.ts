@@ -1,5 +1,5 @@
 import { useQuery } from "@tanstack/react-query";
-import { invoke } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 
 type GetProfileResponse = {
   steamId: string;
@@ -8,9 +8,13 @@ type GetProfileResponse = {
   accountName: string;
 };
 
+/** The backend sends paths of cached pictures, the webview needs asset URLs */
+const toAvatarSrc = (avatarPath: string | null | undefined) =>
+  avatarPath ? convertFileSrc(avatarPath) : undefined;
+
 export const getProfile = async () => {
-  const response = await invoke("get_profile");
-  return response as GetProfileResponse;
+  const response = (await invoke("get_profile")) as GetProfileResponse;
+  return { ...response, avatarUrl: toAvatarSrc(response.avatarUrl) ?? null };
 };
 
 export const useProfile = () =>
@@ -21,28 +25,51 @@ export const useProfile = () =>
 
 export type AccountResponse = {
   username: string;
+  alias: string | null;
   avatarUrl?: string;
+  group: string | null;
+  tags: string[];
+  favourite: boolean;
+};
+
+export type BrokenAccount = {
+  accountName: string;
+  error: { message: string; line: number | null; column: number | null };
 };
 
 export interface AccountsResponse {
   accounts: AccountResponse[];
   activeAccountName: string | null;
+  groups: string[];
+  brokenAccounts: BrokenAccount[];
 }
 
-export const getAccounts = async () => {
-  const response = await invoke("get_accounts");
-  return response as AccountsResponse;
+export const getAccounts = async (group?: string) => {
+  const response = (await invoke("get_accounts", {
+    payload: { group },
+  })) as AccountsResponse;
+  return {
+    ...response,
+    accounts: response.accounts.map((account) => ({
+      ...account,
+      avatarUrl: toAvatarSrc(account.avatarUrl),
+    })),
+  };
 };
 
-export const useAccounts = () =>
+export const useAccounts = (group?: string) =>
   useQuery({
-    queryKey: ["accounts", "all"],
-    queryFn: getAccounts,
+    queryKey: ["accounts", "all", group],
+    queryFn: () => getAccounts(group),
   });
 
 export const getActiveAccount = async () => {
-  const response = await invoke("get_active_account");
-  return response as AccountResponse | null;
+  const response = (await invoke(
+    "get_active_account"
+  )) as AccountResponse | null;
+  return (
+    response && { ...response, avatarUrl: toAvatarSrc(response.avatarUrl) }
+  );
 };
 
 export const useActiveAccount = () =>
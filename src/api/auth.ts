@@ -7,16 +7,32 @@ import {
 import { invoke } from "@tauri-apps/api/core";
 import { z } from "zod/v4";
 
+/** Left empty, the secret stored for an account that logs in again is used */
+const optionalSecret = z
+  .literal("")
+  .transform(() => undefined)
+  .or(z.base64().trim().length(28))
+  .optional();
+
 export const loginRequestSchema = z.object({
   username: z.string().trim().min(1),
   password: z.string().trim().min(1),
-  sharedSecret: z.base64().trim().length(28),
-  identitySecret: z.base64().trim().length(28),
+  sharedSecret: optionalSecret,
+  identitySecret: optionalSecret,
 });
 
-export type LoginRequest = z.infer<typeof loginRequestSchema>;
+export type LoginFormValues = z.input<typeof loginRequestSchema>;
+
+export type LoginRequest = z.output<typeof loginRequestSchema>;
+
+/** What the user has to do next to finish logging in */
+export type LoginStepResponse =
+  | { step: "awaiting-email-code"; emailDomain: string | null }
+  /** Approve the login in the Steam app, then poll */
+  | { step: "awaiting-device-confirmation" }
+  | { step: "done"; accountName: string };
 
-export type LoginResponse = unknown;
+export type PendingLoginStep = Exclude<LoginStepResponse, { step: "done" }>;
 
 export type LoginError = {
   type:
@@ -24,36 +40,96 @@ export type LoginError = {
     | "ValidationError"
     | "OtpError"
     | "IOError"
+    | "Locked"
+    | "SharedSecretRequired"
+    | "InvalidCode"
+    | "NoPendingLogin"
+    | "Cancelled"
+    | "UnexpectedStep"
+    | "PollFailed"
+    | "AccountNotFound"
+    | "AutoReloginDisabled"
+    | "MissingCredentials"
     | "Unimplemented";
   message?: string;
 };
 
 export const loginFullCredentials = async (loginRequest: LoginRequest) => {
-  const response = await invoke<LoginResponse>("login", {
+  const response = await invoke<LoginStepResponse>("login", {
     payload: loginRequest,
   });
   return response;
 };
 
-export const useLoginFullCredentialsMutation = (
-  options?: Omit<
-    UseMutationOptions<LoginResponse, LoginError, LoginRequest>,
-    "mutationKey" | "mutationFn"
-  >
+export const submitLoginEmailCode = async (code: string) => {
+  const response = await invoke<LoginStepResponse>(
+    "submit_login_email_code",
+    { payload: { code } }
+  );
+  return response;
+};
+
+/** Resolves once the login was approved on a device */
+export const pollLogin = async () => {
+  const response = await invoke<LoginStepResponse>("poll_login");
+  return response;
+};
+
+export const cancelLogin = async () => {
+  await invoke<void>("cancel_login");
+};
+
+type LoginStepMutationOptions<TVariables> = Omit<
+  UseMutationOptions<LoginStepResponse, LoginError, TVariables>,
+  "mutationKey" | "mutationFn"
+>;
+
+/** A finished login adds or updates an account, so those queries refetch */
+const useLoginStepMutation = <TVariables>(
+  mutationKey: string,
+  mutationFn: (variables: TVariables) => Promise<LoginStepResponse>,
+  options?: LoginStepMutationOptions<TVariables>
 ) => {
   const queryClient = useQueryClient();
   const { onSuccess, ...rest } = options || {};
-  return useMutation<LoginResponse, LoginError, LoginRequest>({
-    mutationKey: ["auth", "login-full-credentials"],
-    mutationFn: loginFullCredentials,
+  return useMutation<LoginStepResponse, LoginError, TVariables>({
+    mutationKey: ["auth", mutationKey],
+    mutationFn,
     onSuccess: (...args) => {
-      queryClient.invalidateQueries({ queryKey: ["auth"] });
+      if (args[0].step === "done") {
+        queryClient.invalidateQueries({ queryKey: ["auth"] });
+        queryClient.invalidateQueries({ queryKey: ["accounts"] });
+      }
       onSuccess?.(...args);
     },
     ...rest,
   });
 };
 
+export const useLoginFullCredentialsMutation = (
+  options?: LoginStepMutationOptions<LoginRequest>
+) =>
+  useLoginStepMutation("login-full-credentials", loginFullCredentials, options);
+
+export const useSubmitLoginEmailCodeMutation = (
+  options?: LoginStepMutationOptions<string>
+) =>
+  useLoginStepMutation(
+    "submit-login-email-code",
+    submitLoginEmailCode,
+    options
+  );
+
+export const usePollLoginMutation = (
+  options?: LoginStepMutationOptions<void>
+) => useLoginStepMutation("poll-login", pollLogin, options);
+
+export const useCancelLoginMutation = () =>
+  useMutation<void, LoginError, void>({
+    mutationKey: ["auth", "cancel-login"],
+    mutationFn: cancelLogin,
+  });
+
 export type IsLoggedInResponse = boolean;
 
 export const isLoggedIn = async () => {
@@ -5,6 +5,7 @@ import {
   useQueryClient,
 } from "@tanstack/react-query";
 import { invoke } from "@tauri-apps/api/core";
+import type { SteamError } from "./steam-error";
 
 export enum EAuthTokenPlatformType {
   Unknown = "k_EAuthTokenPlatformType_Unknown",
@@ -41,7 +42,7 @@ export type AuthSessionResponse = {
   requestedPersistence?: ESessionPersistence | null;
 };
 
-export type GetApprovalsError = { type: "Unauthorized" } | { type: "Unknown" };
+export type GetApprovalsError = SteamError;
 
 export type ApproveQrLoginRequest = {
   challengeUrl: string;
@@ -68,12 +69,7 @@ export type AuthDenyRequest = {
   clientId: string;
 };
 
-export enum AuthApprovalError {
-  Unauthorized = "unauthorized",
-  Expired = "expired",
-  DuplicateRequest = "duplicate-request",
-  Unknown = "unknown",
-}
+export type AuthApprovalError = SteamError;
 
 export const approveSession = async (
   payload: AuthApproveRequest
@@ -6,6 +6,7 @@ import {
   useQueryClient,
 } from "@tanstack/react-query";
 import { invoke } from "@tauri-apps/api/core";
+import type { SteamError } from "./steam-error";
 
 export enum ConfirmationType {
   Test = "test",
@@ -36,9 +37,7 @@ export type ConfirmationResponse = {
   summary: string[];
 };
 
-export type GetConfirmationsError =
-  | { type: "Unauthorized" }
-  | { type: "ApiError" };
+export type GetConfirmationsError = SteamError;
 
 export const getConfirmations = async (): Promise<ConfirmationResponse[]> => {
   const response = await invoke<ConfirmationResponse[]>("get_confirmations");
@@ -89,12 +88,7 @@ export const useConfirmationDetails = (
     ...options,
   });
 
-export enum ConfirmationError {
-  Unauthorized = "unauthorized",
-  ApiError = "api-error",
-  DeserializationError = "deserialization-error",
-  NetworkFailure = "network-failure",
-}
+export type ConfirmationError = SteamError;
 
 export const acceptConfirmation = async (
   payload: ConfirmationActionRequest
@@ -0,0 +1,10 @@
+/** Returned by every command that calls Steam for the active account */
+export enum SteamError {
+  Unauthorized = "unauthorized",
+  Locked = "locked",
+  Expired = "expired",
+  DuplicateRequest = "duplicate-request",
+  DeserializationError = "deserialization-error",
+  NetworkFailure = "network-failure",
+  ApiError = "api-error",
+}
@@ -0,0 +1,109 @@
+import {
+  type FC,
+  useCallback,
+  useEffect,
+  useState,
+} from "react";
+import {
+  type LoginError,
+  type LoginStepResponse,
+  type PendingLoginStep,
+  useCancelLoginMutation,
+  usePollLoginMutation,
+  useSubmitLoginEmailCodeMutation,
+} from "~/api/auth";
+import { Button } from "~/components/ui/button";
+import { Card } from "~/components/ui/card";
+import { Field, FieldLabel } from "~/components/ui/field";
+import { Input } from "~/components/ui/input";
+import { Spinner } from "~/components/ui/spinner";
+
+type LoginStepProps = {
+  step: PendingLoginStep;
+  onStep: (step: LoginStepResponse) => void;
+  onError: (error: LoginError) => void;
+  onCancel: () => void;
+};
+
+/** Asks for what Steam still needs to finish the login started on the form */
+export const LoginStep: FC<LoginStepProps> = ({
+  step,
+  onStep,
+  onError,
+  onCancel,
+}) => {
+  const [code, setCode] = useState("");
+  const submitCodeMutation = useSubmitLoginEmailCodeMutation();
+  const pollMutation = usePollLoginMutation();
+  const cancelMutation = useCancelLoginMutation();
+  const { mutateAsync: poll } = pollMutation;
+
+  useEffect(() => {
+    if (step.step !== "awaiting-device-confirmation") return;
+    // A poll still running after cancelling must not bring the step back
+    let cancelled = false;
+    poll()
+      .then((next) => {
+        if (!cancelled) onStep(next);
+      })
+      .catch((error: LoginError) => {
+        if (!cancelled) onError(error);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [step, poll, onStep, onError]);
+
+  const handleSubmitCode = useCallback(() => {
+    submitCodeMutation.mutateAsync(code.trim()).then(onStep).catch(onError);
+  }, [code, onStep, onError, submitCodeMutation.mutateAsync]);
+
+  const handleCancel = useCallback(() => {
+    cancelMutation.mutateAsync().finally(onCancel);
+  }, [cancelMutation.mutateAsync, onCancel]);
+
+  return (
+    <Card className="mt-4 w-full gap-3 p-4">
+      {step.step === "awaiting-email-code" ? (
+        // Rendered inside the login form, so this cannot be a form of its own
+        <div className="flex flex-col gap-3">
+          <Field>
+            <FieldLabel htmlFor="steam-email-code">
+              {step.emailDomain
+                ? `Enter the code Steam sent to your ${step.emailDomain} address`
+                : "Enter the code Steam sent to your email address"}
+            </FieldLabel>
+            <Input
+              id="steam-email-code"
+              autoComplete="one-time-code"
+              value={code}
+              onChange={(event) => setCode(event.target.value)}
+            />
+          </Field>
+          <Button
+            type="button"
+            disabled={!code.trim() || submitCodeMutation.isPending}
+            onClick={handleSubmitCode}
+          >
+            {submitCodeMutation.isPending ? "Checking code..." : "Submit code"}
+          </Button>
+        </div>
+      ) : (
+        <div className="flex items-center gap-3">
+          <Spinner />
+          <p className="text-sm">
+            Approve this login in the Steam mobile app to continue
+          </p>
+        </div>
+      )}
+      <Button
+        type="button"
+        variant="ghost"
+        disabled={cancelMutation.isPending}
+        onClick={handleCancel}
+      >
+        Cancel
+      </Button>
+    </Card>
+  );
+};
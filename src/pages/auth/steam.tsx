@@ -1,13 +1,18 @@
 import { zodResolver } from "@hookform/resolvers/zod";
 import { InfoIcon } from "lucide-react";
-import { useCallback } from "react";
+import { useCallback, useState } from "react";
 import { useForm } from "react-hook-form";
 import { toast } from "sonner";
 import {
+  type LoginError,
+  type LoginFormValues,
   type LoginRequest,
+  type LoginStepResponse,
   loginRequestSchema,
+  type PendingLoginStep,
   useLoginFullCredentialsMutation,
 } from "~/api/auth";
+import { LoginStep } from "~/components/auth/login-step";
 import { LookUpInput } from "~/components/lookup-input";
 import { Button } from "~/components/ui/button";
 import { Card } from "~/components/ui/card";
@@ -40,36 +45,63 @@ interface MaFile {
   identity_secret: string;
 }
 
+const loginErrorMessages: Partial<Record<LoginError["type"], string>> = {
+  WrongCredentials: "Wrong username or password",
+  Locked: "Unlock the vault before adding an account",
+  SharedSecretRequired:
+    "Steam asks for a Steam Guard code, enter the shared secret",
+  InvalidCode: "The code was not accepted, try again",
+  Cancelled: "The login was cancelled",
+};
+
 export const AuthSteamPage = () => {
   const loginMutation = useLoginFullCredentialsMutation();
+  const [pendingStep, setPendingStep] = useState<PendingLoginStep | null>(
+    null
+  );
   const {
     register,
     handleSubmit,
     setValue,
     reset,
     formState: { errors },
-  } = useForm<LoginRequest>({
+  } = useForm<LoginFormValues, unknown, LoginRequest>({
     resolver: zodResolver(loginRequestSchema),
   });
 
+  const onStep = useCallback(
+    (response: LoginStepResponse) => {
+      if (response.step !== "done") {
+        setPendingStep(response);
+        return;
+      }
+      setPendingStep(null);
+      toast.success("Steam account added successfully!", {
+        dismissible: true,
+      });
+      reset();
+    },
+    [reset]
+  );
+
+  const onError = useCallback((err: LoginError) => {
+    // Only a wrong email code can be retried, anything else ends the login
+    if (err.type !== "InvalidCode") {
+      setPendingStep(null);
+    }
+    toast.error(
+      loginErrorMessages[err.type] ?? err.message ?? "Failed to add account",
+      { dismissible: true }
+    );
+  }, []);
+
+  const onCancel = useCallback(() => setPendingStep(null), []);
+
   const onSubmit = useCallback(
     async (data: LoginRequest) => {
-      loginMutation
-        .mutateAsync(data)
-        .then(() => {
-          toast.success("Steam account added successfully!", {
-            dismissible: true,
-          });
-          reset();
-        })
-        .catch((err: Error) => {
-          toast.error(
-            err instanceof Error ? err.message : "Failed to add account",
-            { dismissible: true }
-          );
-        });
+      loginMutation.mutateAsync(data).then(onStep).catch(onError);
     },
-    [reset, loginMutation.mutateAsync]
+    [onStep, onError, loginMutation.mutateAsync]
   );
 
   return (
@@ -101,7 +133,7 @@ export const AuthSteamPage = () => {
             </Field>
             <Field>
               <FieldLabel htmlFor="steam-shared-secret">
-                Steam Shared Secret
+                Steam Shared Secret (optional when logging in again)
               </FieldLabel>
               <InputGroup>
                 <LookUpInput
@@ -130,7 +162,7 @@ export const AuthSteamPage = () => {
             </Field>
             <Field>
               <FieldLabel htmlFor="steam-identity-secret">
-                Steam Identity Secret
+                Steam Identity Secret (optional when logging in again)
               </FieldLabel>
               <InputGroup>
                 <LookUpInput
@@ -159,7 +191,7 @@ export const AuthSteamPage = () => {
               <FieldError>{errors.identitySecret?.message}</FieldError>
             </Field>
             <Button
-              disabled={loginMutation.isPending}
+              disabled={loginMutation.isPending || pendingStep !== null}
               type="submit"
               className="mt-4 w-full"
             >
@@ -169,6 +201,14 @@ export const AuthSteamPage = () => {
             </Button>
           </FieldGroup>
         </FieldSet>
+        {pendingStep && (
+          <LoginStep
+            step={pendingStep}
+            onStep={onStep}
+            onError={onError}
+            onCancel={onCancel}
+          />
+        )}
 
         <Separator className="my-6" />
         <Card className="w-full p-4">